@@ -0,0 +1,179 @@
+// This file is a part of Softmacs.
+// Copyright (C) 2018 Matthew Blount
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public
+// License along with this program.  If not, see
+// <https://www.gnu.org/licenses/.
+
+//! Timings for the interpreter's hot paths, run with `cargo bench`.
+//! Each case is named on the command line to run it alone.
+
+#![allow(clippy::needless_return)]
+
+use std::time::{Duration, Instant};
+
+use softmacs::v0::{self, V0};
+use softmacs::Lisp;
+
+/// Run `f` `iterations` times after a warm-up run, and print the mean.
+fn time(name: &str, iterations: u32, mut f: impl FnMut()) {
+  f();
+  let start = Instant::now();
+  for _ in 0..iterations {
+    f();
+  }
+  let each = start.elapsed() / iterations;
+  println!("{:<24} {:>12?}/iter", name, each);
+}
+
+/// A sum of variables bound sixteen frames out, so every lookup goes
+/// through the whole chain unless it was resolved when the sum was
+/// compiled: first evaluated as is, then compiled.
+fn deep_lookup() {
+  let mut lisp = v0::init(1 << 16);
+  let mut src = String::from("($define! deep ");
+  for depth in 0..16 {
+    src.push_str(&format!("(($lambda (x{}) ", depth));
+  }
+  src.push_str("(($vau () e e))");
+  for depth in 0..16 {
+    src.push_str(&format!(") {})", depth));
+  }
+  src.push_str(") deep");
+  let deep = lisp.load_string(&src).unwrap().pop().unwrap();
+  let _deep = lisp.root(deep);
+  let form = lisp.read("(+ x0 x1 x2 x3 x0 x1 x2 x3)").unwrap()[0];
+  let _form = lisp.root(form);
+  time("deep-lookup-eval", 20_000, || {
+    lisp.eval(form, deep).unwrap();
+  });
+  let compiled = lisp.compile(form, deep).unwrap();
+  let _compiled = lisp.root(compiled);
+  time("deep-lookup", 20_000, || {
+    lisp.run_compiled(compiled).unwrap();
+  });
+}
+
+/// A loop body made of special forms and calls, evaluated as is and
+/// then compiled, so the difference is what taking its structure apart
+/// once saves on every later run.
+fn compiled() {
+  let mut lisp = v0::init(1 << 16);
+  let env = lisp.load_string("(($lambda (n) (($vau () e e))) 7)").unwrap().pop().unwrap();
+  let _env = lisp.root(env);
+  let src = "($if (< n 10) ($sequence (* n 2) (+ n (- n 1) ($quote 3))) ($if (= n 0) 1 n))";
+  let form = lisp.read(src).unwrap()[0];
+  let _form = lisp.root(form);
+  time("uncompiled", 20_000, || {
+    lisp.eval(form, env).unwrap();
+  });
+  let compiled = lisp.compile(form, env).unwrap();
+  let _compiled = lisp.root(compiled);
+  time("compiled", 20_000, || {
+    lisp.run_compiled(compiled).unwrap();
+  });
+}
+
+/// Evaluate the form `src` over and over, collecting every thousand
+/// runs so the heap stays about the same size.
+fn repeat(name: &str, lisp: &mut V0, src: &str, iterations: u32) {
+  let form = lisp.read(src).unwrap()[0];
+  let _root = lisp.root(form);
+  let env = lisp.global_env();
+  let mut runs = 0;
+  time(name, iterations, || {
+    lisp.eval(form, env).unwrap();
+    runs += 1;
+    if runs % 1000 == 0 {
+      lisp.gc();
+    }
+  });
+}
+
+/// A million calls to a lambda of one parameter from a `do` loop, each
+/// finding `id` through its call site's cache, and then the same loop
+/// with the cache off so every call looks `id` up again.
+fn calls() {
+  for (name, cached) in [("calls", true), ("calls-uncached", false)] {
+    let mut lisp = v0::init(1 << 16);
+    lisp.set_incremental(true);
+    lisp.set_call_cache(cached);
+    lisp.load_string("($define! id ($lambda (n) n))").unwrap();
+    repeat(name, &mut lisp, "(do ((i 0 (+ i 1))) ((= i 1000000)) (id i))", 1);
+  }
+}
+
+/// Many back to back collections of a small heap with a thousand live
+/// pairs.
+fn collections() {
+  let mut lisp = v0::init(1 << 12);
+  let src = "($define! build ($lambda (n xs) ($if (= n 0) xs (build (- n 1) (pair n xs)))))
+    ($define! kept (build 1000 ()))";
+  lisp.load_string(src).unwrap();
+  time("collections", 200, || {
+    lisp.gc();
+  });
+}
+
+/// The longest pause collecting a heap of a hundred thousand live pairs
+/// plus some garbage: a full `gc`, against the longest slice of an
+/// incremental collection a thousand visits at a time.
+fn pauses() {
+  let mut lisp = v0::init(1 << 18);
+  lisp.load_string("($define! kept (vector->list (make-vector 100000 0)))").unwrap();
+  let garbage = "(vector->list (make-vector 10000 0))";
+  let mut full = Duration::ZERO;
+  for _ in 0..10 {
+    lisp.load_string(garbage).unwrap();
+    let start = Instant::now();
+    lisp.gc();
+    full = full.max(start.elapsed());
+  }
+  let mut slice = Duration::ZERO;
+  for _ in 0..10 {
+    lisp.load_string(garbage).unwrap();
+    loop {
+      let start = Instant::now();
+      let done = lisp.gc_step(1000);
+      slice = slice.max(start.elapsed());
+      if done {
+        break;
+      }
+    }
+  }
+  println!("{:<24} {:>12?} max", "pause-full", full);
+  println!("{:<24} {:>12?} max", "pause-incremental", slice);
+}
+
+/// Nothing but allocation of short-lived lists, a node at a time.
+fn allocation() {
+  let mut lisp = v0::init(1 << 16);
+  repeat("allocation", &mut lisp, "(vector->list (make-vector 256 0))", 50_000);
+}
+
+fn main() {
+  let cases: [(&str, fn()); 6] = [
+    ("deep-lookup", deep_lookup),
+    ("compiled", compiled),
+    ("calls", calls),
+    ("collections", collections),
+    ("pauses", pauses),
+    ("allocation", allocation),
+  ];
+  let names: Vec<String> = std::env::args().skip(1).filter(|arg| !arg.starts_with("--")).collect();
+  for (name, case) in cases.iter() {
+    if names.is_empty() || names.iter().any(|arg| arg == name) {
+      case();
+    }
+  }
+}
@@ -15,7 +15,9 @@
 // License along with this program.  If not, see
 // <https://www.gnu.org/licenses/.
 
+
 use std::rc::Rc;
+use std::path::Path;
 use std::result::Result;
 use std::fmt::Debug;
 
@@ -29,7 +31,20 @@ pub trait Lisp {
   fn symbol(&mut self, value: Rc<str>) -> Result<Self::Value, Self::Error>;
   fn eval(&mut self, value: Self::Value, env: Self::Value) -> Result<Self::Value, Self::Error>;
   fn read(&mut self, src: &str) -> Result<Vec<Self::Value>, Self::Error>;
+  fn load_string(&mut self, src: &str) -> Result<Vec<Self::Value>, Self::Error>;
+  fn load_file(&mut self, path: &Path) -> Result<Vec<Self::Value>, Self::Error>;
+  /// Write `value` in reader syntax, so strings come out quoted.
   fn show(&self, value: Self::Value, buffer: &mut String) -> Result<(), Self::Error>;
+  /// Like `show`, but with strings written as their bare text.
+  fn display(&self, value: Self::Value, buffer: &mut String) -> Result<(), Self::Error>;
+  /// Like `show`, but breaking lines to keep within `width` columns
+  /// where it can. By default there's no breaking at all.
+  fn pretty_show(&self, value: Self::Value, width: usize, buffer: &mut String) -> Result<(), Self::Error> {
+    let _ = width;
+    self.show(value, buffer)
+  }
+  fn gc(&mut self);
+  fn list_iter<'a>(&'a self, value: Self::Value) -> impl Iterator<Item = Result<Self::Value, Self::Error>> + 'a;
 }
 
 pub mod v0;
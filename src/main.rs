@@ -15,27 +15,324 @@
 // License along with this program.  If not, see
 // <https://www.gnu.org/licenses/.
 
+#![allow(clippy::needless_return)]
+
 extern crate softmacs;
 
 use std::io::Write;
+use std::rc::Rc;
 use softmacs::Lisp;
+use softmacs::v0::DebugStep;
+
+/// How many more brackets `src` opens than it closes, ignoring any
+/// inside string literals, char literals like `#\(` and comments.
+fn depth(src: &str) -> isize {
+  let mut depth = 0;
+  let mut runes = src.chars().peekable();
+  while let Some(rune) = runes.next() {
+    match rune {
+      '(' | '[' => { depth += 1 }
+      ')' | ']' => { depth -= 1 }
+      '#' if runes.peek() == Some(&'\\') => {
+        runes.next();
+        runes.next();
+      }
+      ';' => {
+        for rune in runes.by_ref() {
+          if rune == '\n' {
+            break;
+          }
+        }
+      }
+      '"' => {
+        while let Some(rune) = runes.next() {
+          match rune {
+            '"' => { break }
+            '\\' => { runes.next(); }
+            _ => {}
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+  return depth;
+}
+
+/// Append a line of input to `buf`, or say there's no more.
+fn read_line(buf: &mut String) -> bool {
+  let _ = std::io::stdout().flush();
+  match std::io::stdin().read_line(buf) {
+    Ok(0) => {
+      return false;
+    }
+    Ok(_) => {
+      return true;
+    }
+    Err(err) => {
+      eprintln!("error: {}", err);
+      return false;
+    }
+  }
+}
+
+fn report(err: &softmacs::v0::Error, debug: bool) {
+  if debug {
+    eprintln!("error: {:?}", err);
+  } else {
+    eprintln!("error: {}", err);
+  }
+}
+
+/// The REPL's settings and state between `:command`s.
+struct Repl {
+  debug: bool,
+  timing: bool,
+  uid: usize,
+  /// The evaluation `:step` started, if it hasn't finished.
+  stepping: Option<DebugStep>,
+}
+
+/// Say where a `:step` or `:continue` got to.
+fn print_step(lisp: &mut softmacs::v0::V0, step: Result<DebugStep, softmacs::v0::Error>, repl: &mut Repl) {
+  match step {
+    Ok(DebugStep::Done(value)) => {
+      repl.stepping = None;
+      print_value(lisp, value, &mut repl.uid, repl.debug);
+    }
+    Ok(step) => {
+      if let DebugStep::Step { ref description, .. } = step {
+        println!("; {}", description);
+      }
+      repl.stepping = Some(step);
+    }
+    Err(err) => {
+      repl.stepping = None;
+      report(&err, repl.debug);
+    }
+  }
+}
+
+/// Run a `:command` typed at the REPL, and say whether to keep going.
+fn command(lisp: &mut softmacs::v0::V0, line: &str, repl: &mut Repl) -> bool {
+  let debug = repl.debug;
+  let line = line.trim();
+  let (name, operand) = match line.find(char::is_whitespace) {
+    Some(index) => (&line[..index], line[index..].trim()),
+    None => (line, ""),
+  };
+  match name {
+    ":quit" | ":exit" => {
+      return false;
+    }
+    ":load" => {
+      let path = operand.trim_matches('"');
+      if let Err(err) = lisp.load_file(std::path::Path::new(path)) {
+        report(&err, debug);
+      }
+    }
+    ":timing" => {
+      match operand {
+        "on" => { repl.timing = true }
+        "off" => { repl.timing = false }
+        _ => { eprintln!("error: expected :timing on or :timing off") }
+      }
+    }
+    ":gc" => {
+      lisp.gc();
+      println!("{:?}", lisp.stats());
+    }
+    ":heap" => {
+      let mut buf = String::new();
+      match lisp.dump_heap(&mut buf) {
+        Ok(()) => print!("{}", buf),
+        Err(err) => report(&err, debug),
+      }
+    }
+    ":env" => {
+      let env = lisp.global_env();
+      let result = lisp.bindings(env, operand == "sorted").and_then(|bindings| {
+        for (name, value) in bindings {
+          let mut buf = String::new();
+          lisp.show(name, &mut buf)?;
+          buf.push_str(" = ");
+          lisp.show(value, &mut buf)?;
+          println!("{}", buf);
+        }
+        return Ok(());
+      });
+      if let Err(err) = result {
+        report(&err, debug);
+      }
+    }
+    ":step" => {
+      let step = lisp.read(operand).and_then(|forms| {
+        let form = *forms.first().ok_or(softmacs::v0::Error::Read)?;
+        let env = lisp.global_env();
+        return lisp.debug_eval(form, env);
+      });
+      print_step(lisp, step, repl);
+    }
+    ":continue" => {
+      match repl.stepping.take() {
+        Some(step) => {
+          let step = lisp.debug_continue(&step);
+          print_step(lisp, step, repl);
+        }
+        None => { eprintln!("error: nothing to continue; start with :step <expr>") }
+      }
+    }
+    _ => {
+      eprintln!("error: unknown command {}", name);
+    }
+  }
+  return true;
+}
+
+/// Show a value the REPL evaluated, naming it `$N` unless it's unit.
+fn print_value(lisp: &mut softmacs::v0::V0, pointer: softmacs::v0::Gc, uid: &mut usize, debug: bool) {
+  let mut buf = String::new();
+  let result = lisp.show(pointer, &mut buf).and_then(|_| {
+    if lisp.is_unit(pointer)? {
+      println!("{}", &buf);
+      return Ok(());
+    }
+    let name = lisp.symbol(Rc::from(format!("${}", uid).as_str()))?;
+    let env = lisp.global_env();
+    lisp.define(env, name, pointer)?;
+    println!("${} = {}", uid, &buf);
+    *uid += 1;
+    return Ok(());
+  });
+  if let Err(err) = result {
+    report(&err, debug);
+  }
+}
+
+/// Read lines up to one that's just `:end`, or the end of input, for
+/// `:paste` to evaluate all at once.
+fn read_block(buf: &mut String) {
+  let mut line = String::new();
+  loop {
+    line.clear();
+    if !read_line(&mut line) || line.trim() == ":end" {
+      break;
+    }
+    buf.push_str(&line);
+  }
+}
+
+fn usage() -> ! {
+  eprintln!("usage: softmacs [--debug] [--load <file>]... [--eval <expr> | --script <file>]");
+  std::process::exit(2);
+}
+
+fn operand(args: &mut impl Iterator<Item=String>) -> String {
+  return args.next().unwrap_or_else(|| usage());
+}
 
 fn main() {
-  let mut source_buffer = String::new();
-  let mut target_buffer = String::new();
+  let mut debug = false;
+  let mut loads = vec![];
+  let mut eval = None;
+  let mut script = None;
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--debug" => { debug = true }
+      "--load" => { loads.push(operand(&mut args)) }
+      "--eval" => { eval = Some(operand(&mut args)) }
+      "--script" => { script = Some(operand(&mut args)) }
+      _ => { usage() }
+    }
+  }
   let mut lisp = softmacs::v0::init(1024);
-  let mut uid = 0;
+  lisp.set_incremental(true);
+  for path in loads.iter() {
+    if let Err(err) = lisp.load_file(std::path::Path::new(path)) {
+      report(&err, debug);
+      std::process::exit(1);
+    }
+  }
+  if let Some(path) = script {
+    match lisp.load_file(std::path::Path::new(&path)) {
+      Ok(_) => std::process::exit(0),
+      Err(err) => {
+        report(&err, debug);
+        std::process::exit(1);
+      }
+    }
+  }
+  let mut target_buffer = String::new();
+  if let Some(src) = eval {
+    let result = lisp.load_string(&src).and_then(|xs| {
+      for pointer in xs.iter() {
+        target_buffer.clear();
+        lisp.show(*pointer, &mut target_buffer)?;
+        println!("{}", &target_buffer);
+      }
+      return Ok(());
+    });
+    if let Err(err) = result {
+      report(&err, debug);
+      std::process::exit(1);
+    }
+    return;
+  }
+  let mut source_buffer = String::new();
+  let mut repl = Repl { debug, timing: false, uid: 0, stepping: None };
   loop {
     print!("⊥@softmacs\n> ");
     source_buffer.clear();
-    std::io::stdout().flush().unwrap();
-    std::io::stdin().read_line(&mut source_buffer).unwrap();
-    let xs = lisp.read(&source_buffer).unwrap();
-    for pointer in xs.iter() {
-      target_buffer.clear();
-      lisp.show(*pointer, &mut target_buffer).unwrap();
-      println!("{} = {}", format!("${}", uid), &target_buffer);
-      uid += 1;
+    if !read_line(&mut source_buffer) {
+      break;
+    }
+    if source_buffer.trim() == ":paste" {
+      // Everything up to `:end` is one block: no prompts while it's
+      // typed, and only the last value or the first error after.
+      println!("; pasting, :end to finish");
+      source_buffer.clear();
+      read_block(&mut source_buffer);
+      match lisp.load_string(&source_buffer) {
+        Ok(xs) => {
+          if let Some(&pointer) = xs.last() {
+            print_value(&mut lisp, pointer, &mut repl.uid, debug);
+          }
+        }
+        Err(err) => {
+          report(&err, debug);
+        }
+      }
+      continue;
+    }
+    if source_buffer.trim_start().starts_with(':') {
+      if !command(&mut lisp, &source_buffer, &mut repl) {
+        break;
+      }
+      continue;
+    }
+    while depth(&source_buffer) > 0 {
+      print!(".. ");
+      if !read_line(&mut source_buffer) {
+        break;
+      }
+    }
+    let start = std::time::Instant::now();
+    let steps = lisp.step_count();
+    let xs = match lisp.load_string(&source_buffer) {
+      Ok(xs) => xs,
+      Err(err) => {
+        report(&err, debug);
+        continue;
+      }
+    };
+    let elapsed = start.elapsed();
+    let steps = lisp.step_count() - steps;
+    for &pointer in xs.iter() {
+      print_value(&mut lisp, pointer, &mut repl.uid, debug);
+    }
+    if repl.timing {
+      println!("; {:.1}ms, {} steps", elapsed.as_secs_f64() * 1000.0, steps);
     }
   }
 }
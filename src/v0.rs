@@ -15,9 +15,26 @@
 // License along with this program.  If not, see
 // <https://www.gnu.org/licenses/.
 
+#![allow(clippy::needless_return)]
+#![allow(clippy::redundant_field_names)]
+
 use std::rc::Rc;
+use std::any::Any;
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use super::Lisp;
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
+#[cfg(feature = "bignum")]
+use num_integer::Integer;
+#[cfg(feature = "bignum")]
+use num_traits::{One, Signed, ToPrimitive, Zero};
 
 #[derive(Debug, Clone)]
 pub enum Error {
@@ -28,10 +45,58 @@ pub enum Error {
   Type,
   Guard,
   Pointer,
+  Unbound(Rc<str>),
+  Arity { expected: usize, got: usize },
+  Corrupt(Rc<str>),
+  Escape,
+  /// A list opened at `open` was closed by the wrong kind of bracket
+  /// at `close`.
+  Mismatch { open: Span, close: Span },
+  Io(Rc<str>),
+  ReadAt { span: Span, message: Rc<str> },
+  /// A condition was raised with no handler for it, shown here.
+  Uncaught(Rc<str>),
+}
+
+impl std::fmt::Display for Span {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    return write!(f, "{}:{}", self.line, self.col);
+  }
+}
+
+impl std::fmt::Display for Error {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      &Error::Stub => write!(f, "not implemented"),
+      &Error::Read => write!(f, "read error"),
+      &Error::Time => write!(f, "out of time"),
+      &Error::Space => write!(f, "out of space"),
+      &Error::Type => write!(f, "type error"),
+      &Error::Guard => write!(f, "guard failed"),
+      &Error::Pointer => write!(f, "invalid pointer"),
+      Error::Unbound(name) => write!(f, "unbound variable: {}", name),
+      &Error::Arity { expected, got } => write!(f, "wrong number of arguments: expected {}, got {}", expected, got),
+      Error::Corrupt(message) => write!(f, "heap corrupt: {}", message),
+      &Error::Escape => write!(f, "continuation escape"),
+      &Error::Mismatch { open, close } => write!(f, "{}: bracket opened at {} closed by the wrong kind", close, open),
+      Error::Io(message) => write!(f, "io error: {}", message),
+      &Error::ReadAt { span, ref message } => write!(f, "{}: {}", span, message),
+      Error::Uncaught(condition) => write!(f, "uncaught exception: {}", condition),
+    }
+  }
 }
 
+/// Also gives `Box<dyn std::error::Error>` a `From<Error>`, so `?`
+/// can pass an `Error` along as one.
+impl std::error::Error for Error {}
+
 type Result<T> = std::result::Result<T, Error>;
 
+/// `std::io::Error` isn't `Clone`, so only its message is kept.
+fn io_error(err: std::io::Error) -> Error {
+  return Error::Io(Rc::from(err.to_string().as_str()));
+}
+
 fn guard(flag: bool) -> Result<()> {
   if flag {
     return Ok(());
@@ -40,12 +105,24 @@ fn guard(flag: bool) -> Result<()> {
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-struct Gc {
+pub struct Gc {
+  index: usize,
+  timestamp: usize,
+}
+
+/// A `Gc` that doesn't keep its object alive. Collections don't mark
+/// through it, and once the object is swept `upgrade` gives `None`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct WeakGc {
   index: usize,
   timestamp: usize,
 }
 
-type RestFn = Fn(Gc, &mut V0) -> Result<Gc>;
+impl Gc {
+  pub fn downgrade(self) -> WeakGc {
+    return WeakGc { index: self.index, timestamp: self.timestamp };
+  }
+}
 
 #[derive(Clone)]
 struct Symbol(Rc<str>);
@@ -62,13 +139,160 @@ enum Nat {
   Pair,
   Fst,
   Snd,
+  SetFst,
+  Freeze,
+  ValueSize,
+  StorePut,
+  StoreRef,
+  Canonicalize,
+  SandboxEval,
   Eval,
+  TraceEval,
+  Trace,
   Init,
-  Shift,
-  Reset,
   And,
   Or,
   Not,
+  Vau,
+  Lambda,
+  Define,
+  If,
+  Sequence,
+  Quote,
+  Wrap,
+  Unwrap,
+  TreeMap,
+  InternStats,
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Rem,
+  Neg,
+  Abs_,
+  Numerator,
+  Denominator,
+  ExactToInexact,
+  InexactToExact,
+  ValidateHeap,
+  NumEq,
+  NumLt,
+  NumGt,
+  NumLe,
+  NumGe,
+  CallCc,
+  DynamicWind,
+  MakeParameter,
+  Parameterize,
+  DefineSyntax,
+  SyntaxRules,
+  Do,
+  DefineModule,
+  Export,
+  Import,
+  Cons,
+  Car,
+  Cdr,
+  ListProc,
+  Length,
+  Append,
+  Reverse,
+  Map,
+  Filter,
+  Foldl,
+  Foldr,
+  CallEc,
+  Eq,
+  Eqv,
+  Equal,
+  ProperTail,
+  IsNull,
+  IsPair,
+  IsSymbol,
+  IsBool,
+  IsNumber,
+  IsProc,
+  IsEnv,
+  Apply,
+  MakeOperative,
+  MakeApplicative,
+  IsVector,
+  MakeVector,
+  VectorRef,
+  VectorSet,
+  VectorLength,
+  VectorFill,
+  ListToVector,
+  VectorToList,
+  ProcedureSource,
+  IsString,
+  IsChar,
+  ToJson,
+  FromJson,
+  Diff,
+  ReadFile,
+  WriteFile,
+  EnvChain,
+  IsBound,
+  Unbind,
+  EnvBindings,
+  CountLookups,
+  LookupCount,
+  Compile,
+  IsBytevector,
+  IsHashTable,
+  MakeBytevector,
+  Bytevector,
+  BytevectorLength,
+  BytevectorU8Ref,
+  BytevectorU8Set,
+  BytevectorCopy,
+  Utf8Decode,
+  Utf8Encode,
+  Base64Encode,
+  HashBytes,
+  Base64Decode,
+  StringLength,
+  StringRef,
+  StringAppend,
+  Substring,
+  StringToSymbol,
+  SymbolToString,
+  NumberToString,
+  StringToNumber,
+  StringUpcase,
+  StringDowncase,
+  StringEq,
+  StringLt,
+  SbNew,
+  SbAppend,
+  SbBuild,
+  MakeHashTable,
+  HashRef,
+  HashSet,
+  HashDelete,
+  HashContains,
+  HashKeys,
+  HashValues,
+  HashSize,
+  OpenInputFile,
+  OpenOutputFile,
+  ClosePort,
+  ReadChar,
+  PeekChar,
+  WriteChar,
+  WriteString,
+  IsEof,
+  CollectGarbage,
+  Reserve,
+  Gensym,
+  Values,
+  CallWithValues,
+  Receive,
+  Raise,
+  RaiseContinuable,
+  WithExceptionHandler,
+  Guard,
 }
 
 #[derive(Clone)]
@@ -80,6 +304,9 @@ struct Abs {
   tail: Gc,
   lexical: Gc,
   dynamic: Gc,
+  /// The names a call binds, in slot order, when they're known from
+  /// the parameter tree alone; see `slot_names`.
+  names: Option<Rc<[Rc<str>]>>,
 }
 
 #[derive(Clone)]
@@ -87,15 +314,250 @@ enum Proc {
   Nat(Nat),
   App(App),
   Abs(Abs),
+  Cont(Cont),
+  Compiled(Compiled),
+  Param(Param),
+  Traced(Traced),
+}
+
+/// What `(trace name)` rebinds `name` to: an operative that prints each
+/// call before handing it to `proc`.
+#[derive(Clone)]
+struct Traced {
+  name: Gc,
+  proc: Gc,
+}
+
+/// A parameter, whose value is the innermost one `parameterize` gave
+/// it, or `value` outside of any.
+#[derive(Clone)]
+struct Param {
+  value: Gc,
+}
+
+/// A form compiled against `env` by `compile`, run by calling it with
+/// no arguments. Once any binding it was compiled against changes, the
+/// next call compiles `datum` again.
+#[derive(Clone)]
+struct Compiled {
+  datum: Gc,
+  env: Gc,
+  epoch: u64,
+  code: Rc<Code>,
+}
+
+/// The form a compiled node came from, and the `compile_epoch` it was
+/// compiled in. A node whose epoch is out of date evaluates its form
+/// instead, so rebinding an operator partway through a run still takes.
+#[derive(Clone, Copy)]
+struct Source {
+  form: Gc,
+  epoch: u64,
+}
+
+/// Evaluable forms with their operators looked up ahead of time. A
+/// combination whose operator was bound when it was compiled becomes
+/// one of the nodes with a `Source`: the special forms are taken apart
+/// once, applicatives get their operands compiled in turn, and any
+/// other operative is handed its operands as they are. An operator that
+/// wasn't bound, or isn't a symbol, is looked up on every run.
+///
+/// Compiled code only runs in the environment it was compiled against,
+/// so a variable bound there is resolved once, to its lexical address:
+/// `Local` is `depth` parents out, at `offset` in that frame's slots,
+/// and `Cell` is the cell of its binding in a frame alist. A variable
+/// that wasn't bound is a `Var`, looked up by name on every run.
+#[derive(Clone)]
+enum Code {
+  Const(Gc),
+  Var(Rc<str>),
+  Local(Source, usize, usize),
+  Cell(Source, Gc),
+  Combine(Rc<Code>, Gc),
+  Quote(Source, Gc),
+  If(Source, Rc<Code>, Rc<Code>, Rc<Code>),
+  Seq(Source, Rc<[Rc<Code>]>),
+  Define(Source, Gc, Rc<Code>),
+  Call(Source, Gc, Rc<[Rc<Code>]>),
+  Operate(Source, Gc, Gc),
+}
+
+#[derive(Clone)]
+struct Env {
+  frame: Gc,
+  parent: Option<Gc>,
+  slots: Option<Slots>,
+}
+
+/// A frame of fixed names with values by offset, made for calls whose
+/// parameters are known when the closure is. Anything else `define`d
+/// into the environment goes in the alist `frame` as usual, and a slot
+/// `unbind!` empties is `None`.
+#[derive(Clone)]
+struct Slots {
+  names: Rc<[Rc<str>]>,
+  values: Rc<RefCell<Vec<Option<Gc>>>>,
+}
+
+impl Slots {
+  fn offset(&self, name: &str) -> Option<usize> {
+    return self.names.iter().position(|slot| &**slot == name);
+  }
 }
 
 #[derive(Clone)]
 enum Object {
   Unit,
   Bool(bool),
+  Number(i64),
+  /// A ratio of integers in lowest terms, `denom` above 1; any other
+  /// ratio is a `Number`.
+  Rational { numer: i64, denom: i64 },
+  /// An integer too big for a `Number`.
+  #[cfg(feature = "bignum")]
+  BigInt(Rc<BigInt>),
+  /// A `Rational` with a part too big for an `i64`.
+  #[cfg(feature = "bignum")]
+  BigRational { numer: Rc<BigInt>, denom: Rc<BigInt> },
   Symbol(Symbol),
   Pair(Pair),
   Proc(Proc),
+  Env(Env),
+  Vector(Rc<RefCell<Vec<Gc>>>),
+  Str(Rc<str>),
+  Char(char),
+  Bytevector(Rc<RefCell<Vec<u8>>>),
+  Port(Rc<RefCell<Port>>),
+  Eof,
+  /// What `values` returns for anything but exactly one value, which
+  /// it returns as is.
+  MultipleValues(Rc<Vec<Gc>>),
+  Macro(SyntaxRules),
+  /// A hash table, keyed by symbols and strings; see `hash_key`.
+  HashMap(Rc<RefCell<HashMap<String, Gc>>>),
+  Foreign(Foreign),
+  /// The text a string builder has had appended so far; see `sb-new`.
+  Builder(Rc<RefCell<String>>),
+}
+
+/// How `show_mode` writes strings and characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShowMode {
+  /// In reader syntax, quoted and escaped, as `show` does.
+  Write,
+  /// As their bare text, as `display` does.
+  Display,
+}
+
+/// A host value the interpreter only carries around, under the name
+/// of its type; see `V0::foreign`.
+#[derive(Clone)]
+struct Foreign {
+  name: Rc<str>,
+  value: Rc<dyn Any>,
+}
+
+/// How the host shows foreign values of one type; see `V0::set_printer`.
+type Printer = Rc<dyn Fn(&dyn Any, &mut String)>;
+
+/// How the host compares foreign values of one type; see
+/// `V0::set_equality`.
+type Equality = Rc<dyn Fn(&dyn Any, &dyn Any) -> bool>;
+
+/// A host function called with each expression before it's
+/// evaluated, and the interpreter; see `V0::set_trace_hook`.
+pub type TraceHook = Box<dyn Fn(Gc, &V0)>;
+
+/// A `syntax-rules` transformer, defined in `env`. Applying it to the
+/// operands of a form matches them against each rule's pattern, less
+/// its keyword, and evaluates the first match's template, filled in,
+/// in place of the form.
+#[derive(Clone)]
+struct SyntaxRules {
+  literals: Rc<Vec<Rc<str>>>,
+  rules: Rc<Vec<(Gc, Gc)>>,
+  env: Gc,
+}
+
+/// What a pattern variable matched: one form, or under an ellipsis one
+/// binding per repetition.
+#[derive(Clone)]
+enum Binding {
+  One(Gc),
+  Many(Vec<Binding>),
+}
+
+/// A name a macro expansion introduced, renamed so the forms spliced
+/// into it can't capture it or be captured by it. Wherever the
+/// expansion doesn't bind it, it means what `name` means in `env`,
+/// where the macro was defined.
+struct Alias {
+  name: Rc<str>,
+  env: Gc,
+}
+
+/// A stream to read chars from or write text to, or both. `peeked` is
+/// what a `peek-char` saw that no `read-char` has taken yet,
+/// `Some(None)` if that was the end.
+struct Port {
+  reader: Option<Box<dyn Read>>,
+  writer: Option<Box<dyn Write>>,
+  name: Rc<str>,
+  peeked: Option<Option<char>>,
+}
+
+impl Port {
+  fn new(name: &str, reader: Option<Box<dyn Read>>, writer: Option<Box<dyn Write>>) -> Self {
+    return Port { reader: reader, writer: writer, name: Rc::from(name), peeked: None };
+  }
+
+  fn peek(&mut self) -> Result<Option<char>> {
+    if let Some(rune) = self.peeked {
+      return Ok(rune);
+    }
+    let reader = self.reader.as_mut().ok_or(Error::Guard)?;
+    let rune = read_rune(reader)?;
+    self.peeked = Some(rune);
+    return Ok(rune);
+  }
+
+  fn read(&mut self) -> Result<Option<char>> {
+    let rune = self.peek()?;
+    self.peeked = None;
+    return Ok(rune);
+  }
+
+  fn write(&mut self, text: &str) -> Result<()> {
+    let writer = self.writer.as_mut().ok_or(Error::Guard)?;
+    return writer.write_all(text.as_bytes()).map_err(io_error);
+  }
+
+  fn close(&mut self) -> Result<()> {
+    self.reader = None;
+    self.peeked = None;
+    if let Some(mut writer) = self.writer.take() {
+      writer.flush().map_err(io_error)?;
+    }
+    return Ok(());
+  }
+}
+
+/// Decode one UTF-8 char from `reader`, or `None` at the end.
+fn read_rune(reader: &mut dyn Read) -> Result<Option<char>> {
+  let mut buf = [0u8; 4];
+  if reader.read(&mut buf[..1]).map_err(io_error)? == 0 {
+    return Ok(None);
+  }
+  let len = match buf[0] {
+    0x00..=0x7f => 1,
+    0xc0..=0xdf => 2,
+    0xe0..=0xef => 3,
+    0xf0..=0xf7 => 4,
+    _ => return Err(Error::Guard),
+  };
+  reader.read_exact(&mut buf[1..len]).map_err(io_error)?;
+  let text = std::str::from_utf8(&buf[..len]).map_err(|_| Error::Guard)?;
+  return Ok(text.chars().next());
 }
 
 #[derive(Clone)]
@@ -105,42 +567,380 @@ enum Node {
   Mark(Object, usize),
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct HeapStats {
+  pub live: usize,
+  pub free: usize,
+  pub capacity: usize,
+  /// How many separate stretches the free nodes are in; a lot of them
+  /// for not much `free` is what `compact` is for.
+  pub free_runs: usize,
+}
+
 #[derive(Clone)]
 struct Heap {
   nodes: Vec<Node>,
+  /// Timestamps are what make a stale `Gc` detectable, so `time` never
+  /// wraps: once it reaches `usize::MAX` every allocation fails with
+  /// `Error::Space` instead of reissuing a timestamp that some dead
+  /// handle might still carry.
   time: usize,
+  /// When set, `put` fails with `Error::Space` instead of growing past
+  /// this many nodes.
+  max_capacity: Option<usize>,
+  phase: GcPhase,
+  /// The index of every `Node::None` outside the bump region, so `put`
+  /// doesn't have to look for one. A full sweep leaves them lowest last.
+  free: Vec<usize>,
+  /// Which nodes `freeze!` has made immutable, by index.
+  frozen: Vec<bool>,
+  /// The bump region: nodes from `bump` up to `limit` are all free, and
+  /// `put` takes them in order before falling back to `free`. A full
+  /// sweep makes it the longest run of free nodes.
+  bump: usize,
+  limit: usize,
+  /// The gray stack left over from the last collection, kept so the
+  /// next one doesn't have to allocate its own.
+  spare: Vec<Gc>,
+}
+
+/// Where an incremental collection is. Marking holds the gray nodes
+/// still to visit, and sweeping the index of the next node to sweep.
+///
+/// While marking, a `Node::Some` is white, a node on the gray stack is
+/// gray (it may be there more than once, or already black), and a
+/// `Node::Mark` is black. The collector keeps what was reachable when
+/// the cycle began, plus whatever's allocated since, which is born
+/// black. So every mutation that overwrites a pointer in a heap object
+/// has to shade the old pointer first, or an object only reachable
+/// through it could be missed. `Heap::set` does that for pairs,
+/// environments and compiled code, and `define`, `undefine`,
+/// `vector-set!` and `vector-fill!` do it for the slots and vectors
+/// they change in place. Bytevectors, strings and ports hold no
+/// pointers, and nothing else is mutable. Roots need no barrier, since
+/// they're only read when a cycle begins: the stack, pinned values and
+/// so on can change freely while it runs.
+#[derive(Clone)]
+enum GcPhase {
+  Idle,
+  Marking(Vec<Gc>),
+  Sweeping(usize),
 }
 
+/// Node visits an allocation pays toward a collection in progress.
+const GC_STEP_WORK: usize = 16;
+
 impl Object {
   fn is_unit(&self) -> bool {
+    return matches!(self, Object::Unit);
+  }
+
+  /// The name of the variant, for `Heap::dump`.
+  fn kind(&self) -> &'static str {
     match self {
-      &Object::Unit => true,
-      _ => false,
+      &Object::Unit => "Unit",
+      &Object::Bool(_) => "Bool",
+      &Object::Number(_) => "Number",
+      &Object::Rational { .. } => "Rational",
+      #[cfg(feature = "bignum")]
+      &Object::BigInt(_) => "BigInt",
+      #[cfg(feature = "bignum")]
+      &Object::BigRational { .. } => "BigRational",
+      &Object::Symbol(_) => "Symbol",
+      &Object::Pair(_) => "Pair",
+      &Object::Proc(_) => "Proc",
+      &Object::Env(_) => "Env",
+      &Object::Vector(_) => "Vector",
+      &Object::Str(_) => "Str",
+      &Object::Char(_) => "Char",
+      &Object::Bytevector(_) => "Bytevector",
+      &Object::Port(_) => "Port",
+      &Object::Eof => "Eof",
+      &Object::MultipleValues(_) => "MultipleValues",
+      &Object::Macro(_) => "Macro",
+      &Object::HashMap(_) => "HashMap",
+      &Object::Foreign(_) => "Foreign",
+      &Object::Builder(_) => "Builder",
     }
   }
-}
 
-impl Node {
-  fn is_none(&self) -> bool {
+  fn pointers(&self, buf: &mut Vec<Gc>) {
     match self {
-      Node::None => true,
-      _ => false,
+      &Object::Unit => {}
+      &Object::Bool(_) => {}
+      &Object::Number(_) => {}
+      &Object::Rational { .. } => {}
+      #[cfg(feature = "bignum")]
+      &Object::BigInt(_) | &Object::BigRational { .. } => {}
+      &Object::Symbol(_) => {}
+      Object::Pair(value) => {
+        buf.push(value.fst);
+        buf.push(value.snd);
+      }
+      Object::Proc(proc) => {
+        match proc {
+          &Proc::Nat(_) => {}
+          Proc::App(value) => {
+            buf.push(value.0);
+          }
+          Proc::Abs(value) => {
+            buf.push(value.head);
+            buf.push(value.tail);
+            buf.push(value.lexical);
+            buf.push(value.dynamic);
+          }
+          Proc::Cont(value) => {
+            for frame in value.frames.iter() {
+              frame.pointers(buf);
+            }
+            for wind in value.winds.iter() {
+              wind.pointers(buf);
+            }
+          }
+          Proc::Compiled(value) => {
+            buf.push(value.datum);
+            buf.push(value.env);
+            value.code.pointers(buf);
+          }
+          Proc::Param(value) => {
+            buf.push(value.value);
+          }
+          Proc::Traced(value) => {
+            buf.push(value.name);
+            buf.push(value.proc);
+          }
+        }
+      }
+      Object::Env(value) => {
+        buf.push(value.frame);
+        if let Some(parent) = value.parent {
+          buf.push(parent);
+        }
+        if let Some(ref slots) = value.slots {
+          buf.extend(slots.values.borrow().iter().flatten());
+        }
+      }
+      Object::Vector(value) => {
+        buf.extend(value.borrow().iter());
+      }
+      &Object::Str(_) => {}
+      &Object::Char(_) => {}
+      &Object::Bytevector(_) => {}
+      Object::Macro(value) => {
+        for &(pattern, template) in value.rules.iter() {
+          buf.push(pattern);
+          buf.push(template);
+        }
+        buf.push(value.env);
+      }
+      &Object::Port(_) => {}
+      &Object::Foreign(_) => {}
+      &Object::Builder(_) => {}
+      &Object::Eof => {}
+      Object::MultipleValues(values) => {
+        buf.extend(values.iter());
+      }
+      Object::HashMap(table) => {
+        buf.extend(table.borrow().values());
+      }
     }
   }
 
-  fn is_some(&self) -> bool {
+  /// Replace every pointer `pointers` would list with `f` of it.
+  fn rewrite(&mut self, f: &dyn Fn(Gc) -> Gc) {
+    match self {
+      &mut Object::Unit => {}
+      &mut Object::Bool(_) => {}
+      &mut Object::Number(_) => {}
+      &mut Object::Rational { .. } => {}
+      #[cfg(feature = "bignum")]
+      &mut Object::BigInt(_) | &mut Object::BigRational { .. } => {}
+      &mut Object::Symbol(_) => {}
+      &mut Object::Pair(ref mut value) => {
+        value.fst = f(value.fst);
+        value.snd = f(value.snd);
+      }
+      &mut Object::Proc(ref mut proc) => {
+        match *proc {
+          Proc::Nat(_) => {}
+          Proc::App(ref mut value) => {
+            value.0 = f(value.0);
+          }
+          Proc::Abs(ref mut value) => {
+            value.head = f(value.head);
+            value.tail = f(value.tail);
+            value.lexical = f(value.lexical);
+            value.dynamic = f(value.dynamic);
+          }
+          Proc::Cont(ref mut value) => {
+            value.frames = Rc::new(rewrite_frames(&value.frames, f));
+            value.winds = Rc::new(rewrite_winds(&value.winds, f));
+          }
+          Proc::Compiled(ref mut value) => {
+            value.datum = f(value.datum);
+            value.env = f(value.env);
+            value.code = value.code.rewrite(f);
+          }
+          Proc::Param(ref mut value) => {
+            value.value = f(value.value);
+          }
+          Proc::Traced(ref mut value) => {
+            value.name = f(value.name);
+            value.proc = f(value.proc);
+          }
+        }
+      }
+      &mut Object::Env(ref mut value) => {
+        value.frame = f(value.frame);
+        value.parent = value.parent.map(f);
+        if let Some(ref slots) = value.slots {
+          for slot in slots.values.borrow_mut().iter_mut() {
+            *slot = slot.map(f);
+          }
+        }
+      }
+      &mut Object::Vector(ref value) => {
+        for x in value.borrow_mut().iter_mut() {
+          *x = f(*x);
+        }
+      }
+      &mut Object::Str(_) => {}
+      &mut Object::Char(_) => {}
+      &mut Object::Bytevector(_) => {}
+      &mut Object::Macro(ref mut value) => {
+        value.rules = Rc::new(value.rules.iter().map(|&(pattern, template)| (f(pattern), f(template))).collect());
+        value.env = f(value.env);
+      }
+      &mut Object::Port(_) => {}
+      &mut Object::Foreign(_) => {}
+      &mut Object::Builder(_) => {}
+      &mut Object::Eof => {}
+      &mut Object::MultipleValues(ref mut values) => {
+        *values = Rc::new(values.iter().map(|&value| f(value)).collect());
+      }
+      &mut Object::HashMap(ref table) => {
+        for value in table.borrow_mut().values_mut() {
+          *value = f(*value);
+        }
+      }
+    }
+  }
+}
+
+impl Source {
+  fn rewrite(&self, f: &dyn Fn(Gc) -> Gc) -> Source {
+    return Source { form: f(self.form), epoch: self.epoch };
+  }
+}
+
+impl Code {
+  fn source(&self) -> Option<Source> {
     match self {
-      Node::Some(_, _) => true,
-      _ => false,
+      &Code::Const(_) | &Code::Var(_) | &Code::Combine(..) => None,
+      &Code::Local(source, ..) | &Code::Cell(source, _) => Some(source),
+      &Code::Quote(source, _) => Some(source),
+      &Code::If(source, ..) => Some(source),
+      &Code::Seq(source, _) => Some(source),
+      &Code::Define(source, ..) => Some(source),
+      &Code::Call(source, ..) => Some(source),
+      &Code::Operate(source, ..) => Some(source),
     }
   }
 
-  fn is_mark(&self) -> bool {
+  fn pointers(&self, buf: &mut Vec<Gc>) {
+    if let Some(source) = self.source() {
+      buf.push(source.form);
+    }
     match self {
-      Node::Mark(_, _) => true,
-      _ => false,
+      &Code::Const(value) => {
+        buf.push(value);
+      }
+      &Code::Var(_) | &Code::Local(..) => {}
+      &Code::Cell(_, cell) => {
+        buf.push(cell);
+      }
+      &Code::Combine(ref op, operands) => {
+        op.pointers(buf);
+        buf.push(operands);
+      }
+      &Code::Quote(_, value) => {
+        buf.push(value);
+      }
+      Code::If(_, test, conseq, alt) => {
+        test.pointers(buf);
+        conseq.pointers(buf);
+        alt.pointers(buf);
+      }
+      Code::Seq(_, body) => {
+        for code in body.iter() {
+          code.pointers(buf);
+        }
+      }
+      &Code::Define(_, head, ref value) => {
+        buf.push(head);
+        value.pointers(buf);
+      }
+      &Code::Call(_, proc, ref args) => {
+        buf.push(proc);
+        for code in args.iter() {
+          code.pointers(buf);
+        }
+      }
+      &Code::Operate(_, proc, operands) => {
+        buf.push(proc);
+        buf.push(operands);
+      }
     }
   }
+
+  /// A copy with every pointer replaced by `f` of it. Code is shared
+  /// between closures and frames, so it's rebuilt rather than changed.
+  fn rewrite(&self, f: &dyn Fn(Gc) -> Gc) -> Rc<Code> {
+    let code = match self {
+      &Code::Const(value) => Code::Const(f(value)),
+      Code::Var(name) => Code::Var(name.clone()),
+      &Code::Local(source, depth, offset) => Code::Local(source.rewrite(f), depth, offset),
+      &Code::Cell(source, cell) => Code::Cell(source.rewrite(f), f(cell)),
+      &Code::Combine(ref op, operands) => Code::Combine(op.rewrite(f), f(operands)),
+      &Code::Quote(source, value) => Code::Quote(source.rewrite(f), f(value)),
+      &Code::If(source, ref test, ref conseq, ref alt) => {
+        Code::If(source.rewrite(f), test.rewrite(f), conseq.rewrite(f), alt.rewrite(f))
+      }
+      &Code::Seq(source, ref body) => Code::Seq(source.rewrite(f), rewrite_codes(body, f)),
+      &Code::Define(source, head, ref value) => {
+        Code::Define(source.rewrite(f), f(head), value.rewrite(f))
+      }
+      &Code::Call(source, proc, ref args) => {
+        Code::Call(source.rewrite(f), f(proc), rewrite_codes(args, f))
+      }
+      &Code::Operate(source, proc, operands) => {
+        Code::Operate(source.rewrite(f), f(proc), f(operands))
+      }
+    };
+    return Rc::new(code);
+  }
+}
+
+fn rewrite_codes(codes: &[Rc<Code>], f: &dyn Fn(Gc) -> Gc) -> Rc<[Rc<Code>]> {
+  return codes.iter().map(|code| code.rewrite(f)).collect();
+}
+
+fn rewrite_frames(frames: &[Frame], f: &dyn Fn(Gc) -> Gc) -> Vec<Frame> {
+  let mut frames = frames.to_vec();
+  for frame in frames.iter_mut() {
+    frame.rewrite(f);
+  }
+  return frames;
+}
+
+impl Node {
+  fn is_none(&self) -> bool {
+    return matches!(self, Node::None);
+  }
+
+  fn is_some(&self) -> bool {
+    return matches!(self, Node::Some(_, _));
+  }
 }
 
 impl Heap {
@@ -152,20 +952,193 @@ impl Heap {
     Heap {
       nodes: nodes,
       time: 0,
+      max_capacity: None,
+      phase: GcPhase::Idle,
+      free: vec![],
+      frozen: vec![false; capacity],
+      bump: 0,
+      limit: capacity,
+      spare: vec![],
+    }
+  }
+
+  fn is_full(&self) -> bool {
+    return self.free.is_empty() && self.bump == self.limit;
+  }
+
+  /// Whether at most a quarter of the nodes are free, which is when an
+  /// incremental collection has to start if allocation is to finish it
+  /// before the heap fills up.
+  fn is_filling(&self) -> bool {
+    return self.available() <= self.nodes.len() / 4;
+  }
+
+  /// How many nodes `put` can hand out before it has to grow the heap.
+  fn available(&self) -> usize {
+    return self.free.len() + (self.limit - self.bump);
+  }
+
+  /// Double the number of nodes, up to `max_capacity`.
+  fn grow(&mut self) -> Result<()> {
+    let mut capacity = self.nodes.len().saturating_mul(2).max(1);
+    if let Some(max_capacity) = self.max_capacity {
+      capacity = capacity.min(max_capacity);
+    }
+    if capacity <= self.nodes.len() {
+      return Err(Error::Space);
+    }
+    return self.reserve(capacity);
+  }
+
+  /// Grow to at least `capacity` nodes at once, or fail with
+  /// `Error::Space` if that's past `max_capacity`.
+  fn reserve(&mut self, capacity: usize) -> Result<()> {
+    let len = self.nodes.len();
+    if capacity <= len {
+      return Ok(());
     }
+    if self.max_capacity.is_some_and(|max_capacity| capacity > max_capacity) {
+      return Err(Error::Space);
+    }
+    self.nodes.resize_with(capacity, || Node::None);
+    self.frozen.resize(capacity, false);
+    if self.limit != len {
+      self.free.extend((self.bump..self.limit).rev());
+      self.bump = len;
+    }
+    self.limit = capacity;
+    return Ok(());
   }
 
   fn put(&mut self, object: Object) -> Result<Gc> {
-    for (index, node) in self.nodes.iter_mut().enumerate() {
-      if !node.is_none() {
-        continue;
+    let next = self.time.checked_add(1).ok_or(Error::Space)?;
+    if self.is_full() {
+      self.grow()?;
+    }
+    let index = if self.bump < self.limit {
+      self.bump += 1;
+      self.bump - 1
+    } else {
+      self.free.pop().unwrap()
+    };
+    // Anything allocated while a collection is running is born marked,
+    // unless the sweep has already gone past it.
+    self.nodes[index] = match self.phase {
+      GcPhase::Idle => Node::Some(object, self.time),
+      GcPhase::Sweeping(cursor) if index < cursor => Node::Some(object, self.time),
+      _ => Node::Mark(object, self.time),
+    };
+    self.frozen[index] = false;
+    let pointer = Gc { index: index, timestamp: self.time };
+    self.time = next;
+    self.step(GC_STEP_WORK)?;
+    return Ok(pointer);
+  }
+
+  fn is_frozen(&self, pointer: Gc) -> bool {
+    return self.frozen[pointer.index];
+  }
+
+  fn is_idle(&self) -> bool {
+    return matches!(self.phase, GcPhase::Idle);
+  }
+
+  /// Start an incremental collection from `roots`, unless one is
+  /// already running.
+  fn begin(&mut self, roots: &[Gc]) {
+    if self.is_idle() {
+      let mut gray = std::mem::take(&mut self.spare);
+      gray.clear();
+      gray.extend_from_slice(roots);
+      self.phase = GcPhase::Marking(gray);
+    }
+  }
+
+  /// The write barrier: while marking, whatever a mutation is about to
+  /// drop has to be kept for this cycle, since it was live when the
+  /// cycle began.
+  fn shade(&mut self, pointer: Gc) {
+    if let GcPhase::Marking(ref mut gray) = self.phase {
+      gray.push(pointer);
+    }
+  }
+
+  /// Visit at most `work` nodes of the collection in progress, and say
+  /// whether it's finished. `mark` and `sweep` are the batch version.
+  fn step(&mut self, work: usize) -> Result<bool> {
+    let Heap { nodes, time, phase, free, spare, .. } = self;
+    for _ in 0..work {
+      match phase {
+        GcPhase::Idle => {
+          return Ok(true);
+        }
+        GcPhase::Marking(ref mut gray) => {
+          let pointer = match gray.pop() {
+            Some(pointer) => pointer,
+            None => {
+              *spare = std::mem::take(gray);
+              *phase = GcPhase::Sweeping(0);
+              continue;
+            }
+          };
+          let node = &mut nodes[pointer.index];
+          match node {
+            &mut Node::Some(_, timestamp) | &mut Node::Mark(_, timestamp) if pointer.timestamp == timestamp => {}
+            _ => {
+              return Err(Error::Pointer);
+            }
+          }
+          if !node.is_some() {
+            continue;
+          }
+          if let Node::Some(object, timestamp) = std::mem::replace(node, Node::None) {
+            object.pointers(gray);
+            *node = Node::Mark(object, timestamp);
+          }
+        }
+        GcPhase::Sweeping(ref mut cursor) => {
+          let node = match nodes.get_mut(*cursor) {
+            Some(node) => node,
+            None => {
+              *time = time.saturating_add(1);
+              *phase = GcPhase::Idle;
+              return Ok(true);
+            }
+          };
+          *cursor += 1;
+          match std::mem::replace(node, Node::None) {
+            Node::None => {}
+            Node::Some(_, _) => {
+              free.push(*cursor - 1);
+            }
+            Node::Mark(object, timestamp) => {
+              *node = Node::Some(object, timestamp);
+            }
+          }
+        }
+      }
+    }
+    return Ok(self.is_idle());
+  }
+
+  pub fn upgrade(&self, weak: WeakGc) -> Option<Gc> {
+    // Past the sweep cursor an unmarked node is garbage that just
+    // hasn't been freed yet.
+    if let GcPhase::Sweeping(cursor) = self.phase {
+      if let Some(&Node::Some(_, _)) = self.nodes.get(weak.index) {
+        if weak.index >= cursor {
+          return None;
+        }
+      }
+    }
+    match self.nodes.get(weak.index) {
+      Some(&Node::Some(_, timestamp)) | Some(&Node::Mark(_, timestamp)) if timestamp == weak.timestamp => {
+        return Some(Gc { index: weak.index, timestamp: timestamp });
+      }
+      _ => {
+        return None;
       }
-      *node = Node::Some(object, self.time);
-      let pointer = Gc { index: index, timestamp: self.time };
-      self.time += 1;
-      return Ok(pointer);
     }
-    return Err(Error::Space);
   }
 
   fn get(&self, pointer: Gc) -> Result<Object> {
@@ -182,160 +1155,532 @@ impl Heap {
     }
   }
 
-  fn mark(&mut self, pointer: Gc) -> Result<()> {
-    let mut seen = HashSet::new();
-    let mut mark = vec![pointer];
-    let mut to_mark = vec![];
-    while !mark.is_empty() {
-      match &self.nodes[pointer.index] {
-        &Node::None => {
+  /// Overwrite the object at `pointer`, unless `freeze!` has made it
+  /// immutable; mutators `thaw` a frozen node before they get here.
+  fn set(&mut self, pointer: Gc, object: Object) -> Result<()> {
+    guard(!self.is_frozen(pointer))?;
+    if !self.is_idle() {
+      let mut old = vec![];
+      self.get(pointer)?.pointers(&mut old);
+      for pointer in old {
+        self.shade(pointer);
+      }
+    }
+    match &mut self.nodes[pointer.index] {
+      &mut Node::Some(ref mut value, timestamp) | &mut Node::Mark(ref mut value, timestamp) => {
+        if pointer.timestamp != timestamp {
           return Err(Error::Pointer);
         }
-        &Node::Some(ref object, timestamp) | &Node::Mark(ref object, timestamp) => {
-          match object {
-            &Object::Unit => {}
-            &Object::Bool(_) => {}
-            &Object::Symbol(_) => {}
-            &Object::Pair(ref value) => {
-              to_mark.push(value.fst);
-              to_mark.push(value.snd);
-            }
-            &Object::Proc(ref proc) => {
-              match proc {
-                &Proc::Nat(_) => {}
-                &Proc::App(ref value) => {
-                  to_mark.push(value.0);
-                }
-                &Proc::Abs(ref value) => {
-                  to_mark.push(value.head);
-                  to_mark.push(value.tail);
-                  to_mark.push(value.lexical);
-                  to_mark.push(value.dynamic);
-                }
-              }
-            }
+        *value = object;
+        return Ok(());
+      }
+      &mut Node::None => {
+        return Err(Error::Pointer);
+      }
+    }
+  }
+
+  fn mark(&mut self, roots: &[Gc]) -> Result<()> {
+    let mut gray = std::mem::take(&mut self.spare);
+    gray.clear();
+    gray.extend_from_slice(roots);
+    let result = self.mark_gray(&mut gray);
+    self.spare = gray;
+    return result;
+  }
+
+  fn mark_gray(&mut self, gray: &mut Vec<Gc>) -> Result<()> {
+    while let Some(pointer) = gray.pop() {
+      let node = &mut self.nodes[pointer.index];
+      match node {
+        &mut Node::Some(_, timestamp) | &mut Node::Mark(_, timestamp) => {
+          if pointer.timestamp != timestamp {
+            return Err(Error::Pointer);
           }
         }
-      }
-      for pointer in to_mark.iter() {
-        if !seen.contains(pointer) {
-          seen.insert(*pointer);
-          mark.push(*pointer);
+        &mut Node::None => {
+          return Err(Error::Pointer);
         }
       }
-      to_mark.clear();
+      if !node.is_some() {
+        continue;
+      }
+      if let Node::Some(object, timestamp) = std::mem::replace(node, Node::None) {
+        object.pointers(gray);
+        *node = Node::Mark(object, timestamp);
+      }
     }
     return Ok(());
   }
 
-  fn sweep(&mut self) {
-    let mut count = 0;
-    for node in self.nodes.iter_mut() {
-      match node {
-        &mut Node::None => {}
-        &mut Node::Some(ref object, timestamp) => {
-          *node = Node::None;
-          count += 1;
+  /// Check every node reachable from `roots`: that each pointer it
+  /// holds is live, and that a pair's `is_list` agrees with its tail.
+  /// Only what's reachable is checked, since partway through a sweep
+  /// the garbage not yet freed can point at nodes that already are.
+  fn check_invariants(&self, roots: &[Gc]) -> Result<()> {
+    let is_live = |pointer: &Gc| {
+      return match self.nodes.get(pointer.index) {
+        Some(&Node::Some(_, target)) | Some(&Node::Mark(_, target)) => target == pointer.timestamp,
+        _ => false,
+      };
+    };
+    if let Some(root) = roots.iter().find(|root| !is_live(root)) {
+      let message = format!("root {:?} is a dead node", root);
+      return Err(Error::Corrupt(Rc::from(message.as_str())));
+    }
+    let mut seen = vec![false; self.nodes.len()];
+    let mut gray = roots.to_vec();
+    let mut pointers = vec![];
+    while let Some(next) = gray.pop() {
+      if std::mem::replace(&mut seen[next.index], true) {
+        continue;
+      }
+      let (object, timestamp) = match &self.nodes[next.index] {
+        &Node::Some(ref object, timestamp) | &Node::Mark(ref object, timestamp) => (object, timestamp),
+        &Node::None => continue,
+      };
+      let index = next.index;
+      pointers.clear();
+      object.pointers(&mut pointers);
+      for pointer in pointers.iter() {
+        if !is_live(pointer) {
+          let message = format!(
+            "[{} ts={}] points at dead node {:?}", index, timestamp, pointer);
+          return Err(Error::Corrupt(Rc::from(message.as_str())));
         }
-        &mut Node::Mark(ref object, timestamp) => {
-          *node = Node::Some(object.clone(), timestamp);
+      }
+      if let Object::Pair(value) = object {
+        let is_list = match self.get(value.snd)? {
+          Object::Unit => true,
+          Object::Pair(ref snd) => snd.is_list,
+          _ => false,
+        };
+        if value.is_list != is_list {
+          let message = format!(
+            "[{} ts={}] pair has is_list={} but its tail says {}",
+            index, timestamp, value.is_list, is_list);
+          return Err(Error::Corrupt(Rc::from(message.as_str())));
         }
       }
+      gray.extend_from_slice(&pointers);
     }
-    println!("[gc] deleted {} objects", count);
-    self.time += 1;
+    return Ok(());
   }
-}
 
-#[derive(Debug, Clone)]
-enum Token {
-  Lparen,
-  Rparen,
-  Space(Rc<str>),
-  Symbol(Rc<str>),
+  /// Slide every occupied node down to the front, keeping its
+  /// timestamp, and rewrite the pointers inside them. Returns where
+  /// each node went so the caller can fix up pointers held outside the
+  /// heap. Only call it between collections.
+  fn compact(&mut self) -> HashMap<Gc, Gc> {
+    let mut forward = HashMap::new();
+    let mut next = 0;
+    for index in 0..self.nodes.len() {
+      let timestamp = match &self.nodes[index] {
+        &Node::Some(_, timestamp) | &Node::Mark(_, timestamp) => timestamp,
+        &Node::None => continue,
+      };
+      self.nodes.swap(index, next);
+      self.frozen.swap(index, next);
+      forward.insert(Gc { index: index, timestamp: timestamp }, Gc { index: next, timestamp: timestamp });
+      next += 1;
+    }
+    let f = |pointer: Gc| forward.get(&pointer).cloned().unwrap_or(pointer);
+    for node in self.nodes[..next].iter_mut() {
+      if let &mut Node::Some(ref mut object, _) | &mut Node::Mark(ref mut object, _) = node {
+        object.rewrite(&f);
+      }
+    }
+    self.refree();
+    return forward;
+  }
+
+  /// Find the free nodes afresh, making the longest run of them the
+  /// bump region and putting the rest on the free list.
+  fn refree(&mut self) {
+    let nodes = &self.nodes;
+    let (mut bump, mut limit) = (0, 0);
+    let mut start = 0;
+    for index in 0..=nodes.len() {
+      if nodes.get(index).is_some_and(|node| node.is_none()) {
+        continue;
+      }
+      if index - start > limit - bump {
+        (bump, limit) = (start, index);
+      }
+      start = index + 1;
+    }
+    self.bump = bump;
+    self.limit = limit;
+    self.free.clear();
+    self.free.extend((0..nodes.len()).rev().filter(|&index| {
+      return nodes[index].is_none() && !(bump..limit).contains(&index);
+    }));
+  }
+
+  fn sweep(&mut self) -> HeapStats {
+    for node in self.nodes.iter_mut() {
+      match std::mem::replace(node, Node::None) {
+        Node::None | Node::Some(_, _) => {}
+        Node::Mark(object, timestamp) => {
+          *node = Node::Some(object, timestamp);
+        }
+      }
+    }
+    self.refree();
+    self.time = self.time.saturating_add(1);
+    return self.stats();
+  }
+
+  pub fn stats(&self) -> HeapStats {
+    let live = self.nodes.iter().filter(|node| !node.is_none()).count();
+    let mut free_runs = 0;
+    let mut in_run = false;
+    for node in self.nodes.iter() {
+      if node.is_none() && !in_run {
+        free_runs += 1;
+      }
+      in_run = node.is_none();
+    }
+    return HeapStats {
+      live: live,
+      free: self.nodes.len() - live,
+      capacity: self.nodes.len(),
+      free_runs: free_runs,
+    };
+  }
+
+  /// A line for each node in use, with its index and timestamp, and
+  /// whether it's marked. An object that points elsewhere is written
+  /// with its pointers raw, so what keeps what alive can be followed
+  /// by hand; anything else is written by `atom`.
+  fn dump(&self, buf: &mut String, atom: impl Fn(Gc, &mut String) -> Result<()>) -> Result<()> {
+    fn raw(pointer: Gc) -> String {
+      return format!("Gc{{{},{}}}", pointer.index, pointer.timestamp);
+    }
+    for (index, node) in self.nodes.iter().enumerate() {
+      let (object, timestamp, mark) = match *node {
+        Node::None => continue,
+        Node::Some(ref object, timestamp) => (object, timestamp, ""),
+        Node::Mark(ref object, timestamp) => (object, timestamp, " marked"),
+      };
+      buf.push_str(&format!("[{} ts={}{}] ", index, timestamp, mark));
+      let mut pointers = vec![];
+      object.pointers(&mut pointers);
+      match object {
+        Object::Pair(pair) => {
+          buf.push_str(&format!("Pair(fst={}, snd={})", raw(pair.fst), raw(pair.snd)));
+        }
+        _ if pointers.is_empty() => {
+          atom(Gc { index: index, timestamp: timestamp }, buf)?;
+        }
+        _ => {
+          let pointers: Vec<String> = pointers.into_iter().map(raw).collect();
+          buf.push_str(&format!("{}({})", object.kind(), pointers.join(", ")));
+        }
+      }
+      buf.push('\n');
+    }
+    return Ok(());
+  }
+}
+
+struct ListIter<'a> {
+  heap: &'a Heap,
+  xs: Option<Gc>,
+}
+
+impl<'a> Iterator for ListIter<'a> {
+  type Item = Result<Gc>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let xs = self.xs?;
+    match self.heap.get(xs) {
+      Ok(Object::Pair(ref value)) => {
+        self.xs = Some(value.snd);
+        return Some(Ok(value.fst));
+      }
+      Ok(Object::Unit) => {
+        self.xs = None;
+        return None;
+      }
+      Ok(_) => {
+        self.xs = None;
+        return Some(Err(Error::Type));
+      }
+      Err(error) => {
+        self.xs = None;
+        return Some(Err(error));
+      }
+    }
+  }
+}
+
+/// Where a token starts, counting lines and columns from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+  pub line: u32,
+  pub col: u32,
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+  Lparen(Span),
+  Rparen(Span),
+  Lbracket(Span),
+  Rbracket(Span),
+  Space,
+  Symbol(Rc<str>, Span),
+  Str(Rc<str>, Span),
+}
+
+/// Characters with names, as `#\name` reads and writes them.
+const CHAR_NAMES: &[(&str, char)] = &[
+  ("space", ' '),
+  ("newline", '\n'),
+  ("tab", '\t'),
+  ("return", '\r'),
+  ("nul", '\0'),
+];
+
+/// The character `#\name` stands for: a name from `CHAR_NAMES`, or
+/// the one character itself.
+fn char_literal(name: &str) -> Option<char> {
+  if let Some(&(_, rune)) = CHAR_NAMES.iter().find(|&&(known, _)| known == name) {
+    return Some(rune);
+  }
+  let mut runes = name.chars();
+  match (runes.next(), runes.next()) {
+    (Some(rune), None) => return Some(rune),
+    _ => return None,
+  }
+}
+
+fn show_char(rune: char, buf: &mut String) {
+  buf.push_str("#\\");
+  match CHAR_NAMES.iter().find(|&&(_, known)| known == rune) {
+    Some(&(name, _)) => buf.push_str(name),
+    None => buf.push(rune),
+  }
+}
+
+fn read_error(span: Span, message: &str) -> Error {
+  return Error::ReadAt { span: span, message: Rc::from(message) };
+}
+
+/// The number `body` spells, if it's an integer or ratio literal;
+/// an error if it's a ratio over zero.
+fn number_literal(body: &str, lisp: &mut V0) -> Result<Option<Gc>> {
+  if let Ok(value) = body.parse::<i64>() {
+    return Ok(Some(lisp.number(value)?));
+  }
+  if let Some((numer, denom)) = ratio(body) {
+    return Ok(Some(lisp.rational(numer, denom)?));
+  }
+  return big_literal(body, lisp);
+}
+
+/// The integers either side of the slash in a literal like `3/4`.
+fn ratio(body: &str) -> Option<(i64, i64)> {
+  let (numer, denom) = body.split_once('/')?;
+  if denom.starts_with(['+', '-']) {
+    return None;
+  }
+  return Some((numer.parse().ok()?, denom.parse().ok()?));
 }
 
-fn tokenize(src: &Vec<char>) -> Vec<Token> {
-  let mut index = 0;
-  let mut tokens = vec![];
-  while index < src.len() {
-    let rune = src[index];
+/// An integer or ratio literal too big for `parse` and `ratio`.
+#[cfg(feature = "bignum")]
+fn big_literal(body: &str, lisp: &mut V0) -> Result<Option<Gc>> {
+  let (numer, denom) = match body.split_once('/') {
+    Some((_, denom)) if denom.starts_with(['+', '-']) => return Ok(None),
+    Some((numer, denom)) => (numer, denom),
+    None => (body, "1"),
+  };
+  match (numer.parse::<BigInt>(), denom.parse::<BigInt>()) {
+    (Ok(numer), Ok(denom)) => return Ok(Some(lisp.big_rational(numer, denom)?)),
+    _ => return Ok(None),
+  }
+}
+
+#[cfg(not(feature = "bignum"))]
+fn big_literal(_: &str, _: &mut V0) -> Result<Option<Gc>> {
+  return Ok(None);
+}
+
+/// The tokens of some source, read from `runes` one at a time as
+/// they're asked for. A `;` comment to the end of the line is space.
+/// After an error it yields nothing more.
+struct Tokens<I: Iterator<Item = char>> {
+  runes: std::iter::Peekable<I>,
+  /// Where the next rune is.
+  span: Span,
+  failed: bool,
+}
+
+impl<I: Iterator<Item = char>> Tokens<I> {
+  fn new(runes: I) -> Self {
+    return Tokens { runes: runes.peekable(), span: Span { line: 1, col: 1 }, failed: false };
+  }
+
+  fn peek(&mut self) -> Option<char> {
+    return self.runes.peek().cloned();
+  }
+
+  fn bump(&mut self) -> Option<char> {
+    let rune = self.runes.next()?;
+    if rune == '\n' {
+      self.span = Span { line: self.span.line + 1, col: 1 };
+    } else {
+      self.span.col += 1;
+    }
+    return Some(rune);
+  }
+
+  fn token(&mut self, rune: char) -> Result<Token> {
+    let start = self.span;
     match rune {
       '(' => {
-        tokens.push(Token::Lparen);
-        index += 1;
+        self.bump();
+        return Ok(Token::Lparen(start));
       }
       ')' => {
-        tokens.push(Token::Rparen);
-        index += 1;
+        self.bump();
+        return Ok(Token::Rparen(start));
+      }
+      '[' => {
+        self.bump();
+        return Ok(Token::Lbracket(start));
+      }
+      ']' => {
+        self.bump();
+        return Ok(Token::Rbracket(start));
       }
       ' ' | '\t' | '\r' | '\n' => {
-        let mut buf = String::new();
-        while index < src.len() {
-          let rune = src[index];
+        while let Some(rune) = self.peek() {
           match rune {
             ' ' | '\t' | '\r' | '\n' => {
-              buf.push(rune);
-              index += 1;
+              self.bump();
             }
             _ => { break }
           }
         }
-        let space = Rc::from(buf.as_str());
-        tokens.push(Token::Space(space));
+        return Ok(Token::Space);
+      }
+      ';' => {
+        while let Some(rune) = self.bump() {
+          if rune == '\n' {
+            break;
+          }
+        }
+        return Ok(Token::Space);
+      }
+      '"' => {
+        let mut buf = String::new();
+        self.bump();
+        loop {
+          let escape = self.span;
+          let rune = self.bump().ok_or_else(|| read_error(start, "unterminated string"))?;
+          match rune {
+            '"' => { break }
+            '\\' => {
+              let rune = self.bump().ok_or_else(|| read_error(start, "unterminated string"))?;
+              match rune {
+                'n' => { buf.push('\n') }
+                't' => { buf.push('\t') }
+                'r' => { buf.push('\r') }
+                '"' | '\\' => { buf.push(rune) }
+                _ => { return Err(read_error(escape, "unknown escape")) }
+              }
+            }
+            _ => {
+              buf.push(rune);
+            }
+          }
+        }
+        return Ok(Token::Str(Rc::from(buf.as_str()), start));
       }
       _ => {
         let mut buf = String::new();
-        while index < src.len() {
-          let rune = src[index];
+        while let Some(rune) = self.peek() {
           match rune {
-            '(' | ')' | ' ' | '\t' | '\r' | '\n' => { break }
+            '(' | ')' | '[' | ']' | '"' | ';' | ' ' | '\t' | '\r' | '\n' => { break }
             _ => {
               buf.push(rune);
-              index += 1;
+              self.bump();
+              // `#\` takes the character after it, whatever it is.
+              if buf == "#\\" {
+                if let Some(rune) = self.bump() {
+                  buf.push(rune);
+                }
+              }
             }
           }
         }
         let body = Rc::from(buf.as_str());
-        let token = Token::Symbol(body);
-        tokens.push(token);
+        return Ok(Token::Symbol(body, start));
       }
     }
   }
-  return tokens;
 }
 
-fn parse(src: &Vec<Token>, lisp: &mut V0) -> Result<Vec<Gc>> {
-  let mut index = 0;
+impl<I: Iterator<Item = char>> Iterator for Tokens<I> {
+  type Item = Result<Token>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.failed {
+      return None;
+    }
+    let rune = self.peek()?;
+    let token = self.token(rune);
+    self.failed = token.is_err();
+    return Some(token);
+  }
+}
+
+/// All the tokens of `src` at once; see `Tokens`.
+#[allow(dead_code)]
+fn tokenize(src: &str) -> Result<Vec<Token>> {
+  return Tokens::new(src.chars()).collect();
+}
+
+/// The values `tokens` spell, pulling each token only once the last
+/// one has been dealt with.
+fn parse(mut tokens: impl Iterator<Item = Result<Token>>, lisp: &mut V0) -> Result<Vec<Gc>> {
   let mut pointers = vec![];
   let mut stack = vec![];
-  while index < src.len() {
-    match &src[index] {
-      &Token::Lparen => {
-        stack.push(pointers);
+  while let Some(token) = tokens.next() {
+    let token = token?;
+    match &token {
+      &Token::Lparen(open) | &Token::Lbracket(open) => {
+        let square = matches!(token, Token::Lbracket(_));
+        stack.push((pointers, open, square));
         pointers = vec![];
-        index += 1;
       }
-      &Token::Rparen => {
+      &Token::Rparen(close) | &Token::Rbracket(close) => {
+        let square = matches!(token, Token::Rbracket(_));
         match stack.pop() {
-          Some(prev) => {
+          Some((prev, open, opened_square)) => {
+            if square != opened_square && !lisp.loose_brackets {
+              return Err(Error::Mismatch { open: open, close: close });
+            }
             let mut xs = lisp.unit()?;
             for pointer in pointers.iter().rev() {
               xs = lisp.pair(*pointer, xs)?;
             }
             pointers = prev;
             pointers.push(xs);
-            index += 1;
           }
           None => {
-            return Err(Error::Read);
+            return Err(read_error(close, "unmatched close bracket"));
           }
         }
       }
-      &Token::Space(ref body) => {
-        index += 1;
+      &Token::Space => {}
+      Token::Str(body, _) => {
+        let pointer = lisp.heap.put(Object::Str(body.clone()))?;
+        pointers.push(pointer);
       }
-      &Token::Symbol(ref body) => {
+      &Token::Symbol(ref body, span) => {
         let pointer;
         if body.starts_with("#") {
           match &**body {
@@ -348,164 +1693,7269 @@ fn parse(src: &Vec<Token>, lisp: &mut V0) -> Result<Vec<Gc>> {
             "#f" => {
               pointer = lisp.f()?;
             }
+            _ if body.starts_with("#\\") => {
+              let rune = char_literal(&body[2..]).ok_or_else(|| read_error(span, "unknown character"))?;
+              pointer = lisp.heap.put(Object::Char(rune))?;
+            }
+            "#u8" => {
+              match tokens.next().transpose()? {
+                Some(Token::Lparen(_)) => {}
+                _ => return Err(read_error(span, "expected ( after #u8")),
+              }
+              let mut bytes = vec![];
+              loop {
+                match tokens.next().transpose()?.as_ref() {
+                  Some(&Token::Space) => {}
+                  Some(&Token::Symbol(ref byte, at)) => {
+                    bytes.push(byte.parse::<u8>().map_err(|_| read_error(at, "not a byte"))?);
+                  }
+                  Some(&Token::Rparen(_)) => { break }
+                  Some(&Token::Lparen(at)) | Some(&Token::Lbracket(at))
+                  | Some(&Token::Rbracket(at)) | Some(&Token::Str(_, at)) => {
+                    return Err(read_error(at, "not a byte"));
+                  }
+                  None => return Err(read_error(span, "unterminated bytevector")),
+                }
+              }
+              pointer = lisp.heap.put(Object::Bytevector(Rc::new(RefCell::new(bytes))))?;
+            }
             _ => {
-              return Err(Error::Read);
+              return Err(read_error(span, "unknown # literal"));
             }
           }
+        } else if let Some(value) = number_literal(body, lisp).map_err(|error| match error {
+          Error::Type => read_error(span, "zero denominator"),
+          error => error,
+        })? {
+          pointer = value;
         } else {
           pointer = lisp.symbol(body.clone())?;
         }
         pointers.push(pointer);
-        index += 1;
       }
     }
   }
+  if let Some((_, open, _)) = stack.pop() {
+    return Err(read_error(open, "unclosed open bracket"));
+  }
   return Ok(pointers);
 }
 
-fn eval(
-  value: Gc,
-  env: Gc,
-  lisp: &mut V0,
-  rest: &RestFn) -> Result<Gc> {
-  return Err(Error::Stub);
+#[derive(Clone)]
+enum Frame {
+  Combine { operands: Gc, env: Gc },
+  Evlis { proc: Gc, todo: Gc, done: Gc, env: Gc },
+  Exec { body: Gc, env: Gc },
+  If { conseq: Gc, alt: Gc, env: Gc },
+  Define { head: Gc, env: Gc },
+  Extent { id: usize },
+  Branch { conseq: Rc<Code>, alt: Rc<Code>, env: Gc },
+  Seq { body: Rc<[Rc<Code>]>, next: usize, env: Gc },
+  Args { proc: Gc, todo: Rc<[Rc<Code>]>, next: usize, done: Gc, env: Gc },
+  Consume { consumer: Gc, env: Gc },
+  Receive { formals: Gc, body: Gc, env: Gc },
+  /// The handler `with-exception-handler` installed for everything
+  /// above this frame.
+  Handler { handler: Gc, env: Gc },
+  /// A handler is running for `condition`, with the ones from `depth`
+  /// up out of the way.
+  Raised { depth: usize, condition: Gc, continuable: bool },
+  /// A `guard` body is running; a raise unwinds to here.
+  Guard { var: Gc, clauses: Gc, env: Gc },
+  Caught { var: Gc, clauses: Gc, env: Gc },
+  Clause { clause: Gc, rest: Gc, condition: Gc, env: Gc },
+  /// `dynamic-wind` is running its `before`.
+  Winding { before: Gc, during: Gc, after: Gc, env: Gc },
+  /// `parameterize` is evaluating its parameters and their values.
+  Parameterize { body: Gc, env: Gc },
+  /// The `during` of the innermost entry of `V0::winds`, or the body
+  /// of a `parameterize`, is running.
+  Wound,
+  /// An `after` is running, on the way out with `value`.
+  Unwinding { value: Gc },
+  /// An `after` or `before` is running on the way to `escape`.
+  Travel { escape: Rc<Escape>, entering: bool },
+  /// The body of the module `name` is running in `env`, and has
+  /// exported `exports` so far.
+  Module { name: Rc<str>, env: Gc, exports: Vec<Gc> },
+  /// A `do` loop is evaluating the values for its next iteration's
+  /// bindings: the inits, then the steps.
+  DoBind { spec: Loop },
+  /// A `do` loop is evaluating its test in this iteration's `env`.
+  DoTest { spec: Loop, env: Gc },
+  /// A `do` loop is running its body in this iteration's `env`.
+  DoBody { spec: Loop, env: Gc },
+  /// `trace-eval` is evaluating its form, and makes what `trace` saw
+  /// into a tree once it's done.
+  TraceEval { trace: usize },
+  /// The evaluation `node` of `trace` is running; see `TraceNode`.
+  Traced { trace: usize, node: usize },
 }
 
-fn exec(
-  value: Gc,
-  env: Gc,
-  lisp: &mut V0,
-  rest: &RestFn) -> Result<Gc> {
-  return Err(Error::Stub);
+/// An evaluation a `trace-eval` saw: of what, to what once it's done,
+/// and the evaluation it happened during, if it's not the first.
+struct TraceNode {
+  expr: Gc,
+  result: Option<Gc>,
+  parent: Option<usize>,
 }
 
-fn evlis(
-  value: Gc,
+/// A `do` form, taken apart, and the environment it's in. A variable
+/// with no step steps to itself.
+#[derive(Clone, Copy)]
+struct Loop {
+  vars: Gc,
+  steps: Gc,
+  test: Gc,
+  results: Gc,
+  body: Gc,
   env: Gc,
-  lisp: &mut V0,
-  rest: &RestFn) -> Result<Gc> {
-  return Err(Error::Stub);
 }
 
-fn apply(
-  proc: Gc,
-  value: Gc,
-  env: Gc,
-  lisp: &mut V0,
-  rest: &RestFn) -> Result<Gc> {
-  return Err(Error::Stub);
-}
+impl Loop {
+  fn pointers(&self, buf: &mut Vec<Gc>) {
+    buf.extend([self.vars, self.steps, self.test, self.results, self.body, self.env]);
+  }
 
-struct V0 {
-  heap: Heap,
+  fn rewrite(&mut self, f: &dyn Fn(Gc) -> Gc) {
+    self.vars = f(self.vars);
+    self.steps = f(self.steps);
+    self.test = f(self.test);
+    self.results = f(self.results);
+    self.body = f(self.body);
+    self.env = f(self.env);
+  }
 }
 
-impl super::Lisp for V0 {
-  type Value = Gc;
-  type Error = Error;
-
-  fn unit(&mut self) -> Result<Self::Value> {
-    let object = Object::Unit;
-    return self.heap.put(object);
+impl Frame {
+  fn pointers(&self, buf: &mut Vec<Gc>) {
+    match self {
+      &Frame::Combine { operands, env } => {
+        buf.push(operands);
+        buf.push(env);
+      }
+      &Frame::Evlis { proc, todo, done, env } => {
+        buf.push(proc);
+        buf.push(todo);
+        buf.push(done);
+        buf.push(env);
+      }
+      &Frame::Exec { body, env } => {
+        buf.push(body);
+        buf.push(env);
+      }
+      &Frame::If { conseq, alt, env } => {
+        buf.push(conseq);
+        buf.push(alt);
+        buf.push(env);
+      }
+      &Frame::Define { head, env } => {
+        buf.push(head);
+        buf.push(env);
+      }
+      &Frame::Extent { .. } => {}
+      &Frame::Branch { ref conseq, ref alt, env } => {
+        conseq.pointers(buf);
+        alt.pointers(buf);
+        buf.push(env);
+      }
+      &Frame::Seq { ref body, env, .. } => {
+        for code in body.iter() {
+          code.pointers(buf);
+        }
+        buf.push(env);
+      }
+      &Frame::Args { proc, ref todo, done, env, .. } => {
+        buf.push(proc);
+        for code in todo.iter() {
+          code.pointers(buf);
+        }
+        buf.push(done);
+        buf.push(env);
+      }
+      &Frame::Consume { consumer, env } => {
+        buf.push(consumer);
+        buf.push(env);
+      }
+      &Frame::Receive { formals, body, env } => {
+        buf.push(formals);
+        buf.push(body);
+        buf.push(env);
+      }
+      &Frame::Handler { handler, env } => {
+        buf.push(handler);
+        buf.push(env);
+      }
+      &Frame::Raised { condition, .. } => {
+        buf.push(condition);
+      }
+      &Frame::Guard { var, clauses, env } | &Frame::Caught { var, clauses, env } => {
+        buf.push(var);
+        buf.push(clauses);
+        buf.push(env);
+      }
+      &Frame::Clause { clause, rest, condition, env } => {
+        buf.push(clause);
+        buf.push(rest);
+        buf.push(condition);
+        buf.push(env);
+      }
+      &Frame::Winding { before, during, after, env } => {
+        buf.push(before);
+        buf.push(during);
+        buf.push(after);
+        buf.push(env);
+      }
+      &Frame::Parameterize { body, env } => {
+        buf.push(body);
+        buf.push(env);
+      }
+      &Frame::Wound => {}
+      &Frame::Unwinding { value } => {
+        buf.push(value);
+      }
+      Frame::Travel { escape, .. } => {
+        escape.pointers(buf);
+      }
+      &Frame::Module { env, ref exports, .. } => {
+        buf.push(env);
+        buf.extend(exports.iter());
+      }
+      Frame::DoBind { spec } => {
+        spec.pointers(buf);
+      }
+      &Frame::DoTest { ref spec, env } | &Frame::DoBody { ref spec, env } => {
+        spec.pointers(buf);
+        buf.push(env);
+      }
+      &Frame::TraceEval { .. } | &Frame::Traced { .. } => {}
+    }
   }
 
-  fn t(&mut self) -> Result<Self::Value> {
-    let object = Object::Bool(true);
-    return self.heap.put(object);
+  fn rewrite(&mut self, f: &dyn Fn(Gc) -> Gc) {
+    match self {
+      &mut Frame::Combine { ref mut operands, ref mut env } => {
+        *operands = f(*operands);
+        *env = f(*env);
+      }
+      &mut Frame::Evlis { ref mut proc, ref mut todo, ref mut done, ref mut env } => {
+        *proc = f(*proc);
+        *todo = f(*todo);
+        *done = f(*done);
+        *env = f(*env);
+      }
+      &mut Frame::Exec { ref mut body, ref mut env } => {
+        *body = f(*body);
+        *env = f(*env);
+      }
+      &mut Frame::If { ref mut conseq, ref mut alt, ref mut env } => {
+        *conseq = f(*conseq);
+        *alt = f(*alt);
+        *env = f(*env);
+      }
+      &mut Frame::Define { ref mut head, ref mut env } => {
+        *head = f(*head);
+        *env = f(*env);
+      }
+      &mut Frame::Extent { .. } => {}
+      &mut Frame::Branch { ref mut conseq, ref mut alt, ref mut env } => {
+        *conseq = conseq.rewrite(f);
+        *alt = alt.rewrite(f);
+        *env = f(*env);
+      }
+      &mut Frame::Seq { ref mut body, ref mut env, .. } => {
+        *body = rewrite_codes(body, f);
+        *env = f(*env);
+      }
+      &mut Frame::Args { ref mut proc, ref mut todo, ref mut done, ref mut env, .. } => {
+        *proc = f(*proc);
+        *todo = rewrite_codes(todo, f);
+        *done = f(*done);
+        *env = f(*env);
+      }
+      &mut Frame::Consume { ref mut consumer, ref mut env } => {
+        *consumer = f(*consumer);
+        *env = f(*env);
+      }
+      &mut Frame::Receive { ref mut formals, ref mut body, ref mut env } => {
+        *formals = f(*formals);
+        *body = f(*body);
+        *env = f(*env);
+      }
+      &mut Frame::Handler { ref mut handler, ref mut env } => {
+        *handler = f(*handler);
+        *env = f(*env);
+      }
+      &mut Frame::Raised { ref mut condition, .. } => {
+        *condition = f(*condition);
+      }
+      &mut Frame::Guard { ref mut var, ref mut clauses, ref mut env }
+      | &mut Frame::Caught { ref mut var, ref mut clauses, ref mut env } => {
+        *var = f(*var);
+        *clauses = f(*clauses);
+        *env = f(*env);
+      }
+      &mut Frame::Clause { ref mut clause, ref mut rest, ref mut condition, ref mut env } => {
+        *clause = f(*clause);
+        *rest = f(*rest);
+        *condition = f(*condition);
+        *env = f(*env);
+      }
+      &mut Frame::Winding { ref mut before, ref mut during, ref mut after, ref mut env } => {
+        *before = f(*before);
+        *during = f(*during);
+        *after = f(*after);
+        *env = f(*env);
+      }
+      &mut Frame::Parameterize { ref mut body, ref mut env } => {
+        *body = f(*body);
+        *env = f(*env);
+      }
+      &mut Frame::Wound => {}
+      &mut Frame::Unwinding { ref mut value } => {
+        *value = f(*value);
+      }
+      &mut Frame::Travel { ref mut escape, .. } => {
+        let mut copy = (**escape).clone();
+        copy.rewrite(f);
+        *escape = Rc::new(copy);
+      }
+      &mut Frame::Module { ref mut env, ref mut exports, .. } => {
+        *env = f(*env);
+        for export in exports.iter_mut() {
+          *export = f(*export);
+        }
+      }
+      &mut Frame::DoBind { ref mut spec } => {
+        spec.rewrite(f);
+      }
+      &mut Frame::DoTest { ref mut spec, ref mut env } | &mut Frame::DoBody { ref mut spec, ref mut env } => {
+        spec.rewrite(f);
+        *env = f(*env);
+      }
+      &mut Frame::TraceEval { .. } | &mut Frame::Traced { .. } => {}
+    }
   }
+}
 
-  fn f(&mut self) -> Result<Self::Value> {
-    let object = Object::Bool(false);
-    return self.heap.put(object);
+struct Run {
+  id: usize,
+  base: usize,
+}
+
+/// A captured control stack. Native code that calls back into Lisp
+/// starts a nested `run`, and a continuation belongs to the run that
+/// captured it: invoking it from inside a deeper run unwinds out to its
+/// own, and invoking it after its run has returned is `Error::Guard`.
+/// An escape continuation captures no frames, only the depth of an
+/// `Extent` marker, and is dead once that marker has been popped.
+/// Invoking either kind leaves and re-enters `dynamic-wind`s as needed
+/// to get back inside the ones in `winds`.
+#[derive(Clone)]
+struct Cont {
+  run: usize,
+  depth: usize,
+  frames: Rc<Vec<Frame>>,
+  extent: Option<usize>,
+  winds: Rc<Vec<Wind>>,
+}
+
+/// Where control is going: to `depth` of the stack of run `run`, with
+/// `frames` on top, returning `value`, once the winds have been
+/// unwound and rewound to `winds`; see `travel`.
+#[derive(Clone)]
+struct Escape {
+  run: usize,
+  depth: usize,
+  frames: Rc<Vec<Frame>>,
+  value: Gc,
+  winds: Rc<Vec<Wind>>,
+}
+
+impl Escape {
+  fn pointers(&self, buf: &mut Vec<Gc>) {
+    buf.push(self.value);
+    for frame in self.frames.iter() {
+      frame.pointers(buf);
+    }
+    for wind in self.winds.iter() {
+      wind.pointers(buf);
+    }
   }
 
-  fn symbol(
-    &mut self,
-    value: Rc<str>) -> Result<Self::Value> {
-    let symbol = Symbol(value);
-    let object = Object::Symbol(symbol);
-    return self.heap.put(object);
+  fn rewrite(&mut self, f: &dyn Fn(Gc) -> Gc) {
+    self.value = f(self.value);
+    self.frames = Rc::new(rewrite_frames(&self.frames, f));
+    self.winds = Rc::new(rewrite_winds(&self.winds, f));
   }
+}
 
-  fn pair(
-    &mut self,
-    fst: Self::Value,
-    snd: Self::Value) -> Result<Self::Value> {
-    let is_list: bool;
-    match self.heap.get(snd)? {
-      Object::Unit            => { is_list = true }
-      Object::Pair(ref value) => { is_list = value.is_list }
-      _                       => { is_list = false }
+/// A `dynamic-wind` whose `during` is running, with its `Wound` frame
+/// at `depth` of the stack. A `parameterize` is one with no thunks,
+/// whose `bindings` are pushed onto `V0::params` on the way in and
+/// popped on the way out.
+#[derive(Clone)]
+struct Wind {
+  id: usize,
+  depth: usize,
+  before: Option<Gc>,
+  after: Option<Gc>,
+  env: Gc,
+  bindings: Rc<Vec<(Gc, Gc)>>,
+}
+
+impl Wind {
+  fn pointers(&self, buf: &mut Vec<Gc>) {
+    buf.extend(self.before);
+    buf.extend(self.after);
+    buf.push(self.env);
+    for &(param, value) in self.bindings.iter() {
+      buf.push(param);
+      buf.push(value);
     }
-    let pair = Pair { fst: fst, snd: snd, is_list: is_list };
-    let object = Object::Pair(pair);
-    return self.heap.put(object);
   }
+}
 
-  fn eval(
-    &mut self,
-    value: Self::Value,
-    env: Self::Value) -> Result<Self::Value> {
-    return eval(value, env, self, &|value, lisp| Ok(value));
+fn rewrite_winds(winds: &[Wind], f: &dyn Fn(Gc) -> Gc) -> Vec<Wind> {
+  let mut winds = winds.to_vec();
+  for wind in winds.iter_mut() {
+    wind.before = wind.before.map(f);
+    wind.after = wind.after.map(f);
+    wind.env = f(wind.env);
+    wind.bindings = Rc::new(wind.bindings.iter().map(|&(param, value)| (f(param), f(value))).collect());
   }
+  return winds;
+}
 
-  fn read(
-    &mut self,
-    src: &str) -> Result<Vec<Self::Value>> {
-    let runes = src.chars().collect();
-    let tokens = tokenize(&runes);
-    return parse(&tokens, self);
+enum Step {
+  Eval(Gc, Gc),
+  Exec(Gc, Gc),
+  Apply(Gc, Gc, Gc),
+  Run(Rc<Code>, Gc),
+  Return(Gc),
+}
+
+impl Step {
+  fn pointers(&self, buf: &mut Vec<Gc>) {
+    match self {
+      &Step::Eval(value, env) | &Step::Exec(value, env) => {
+        buf.push(value);
+        buf.push(env);
+      }
+      &Step::Apply(proc, value, env) => {
+        buf.push(proc);
+        buf.push(value);
+        buf.push(env);
+      }
+      &Step::Run(ref code, env) => {
+        code.pointers(buf);
+        buf.push(env);
+      }
+      &Step::Return(value) => {
+        buf.push(value);
+      }
+    }
   }
 
-  fn show(
-    &self,
-    pointer: Self::Value,
-    buf: &mut String) -> Result<()> {
-    match self.heap.get(pointer)? {
-      Object::Unit => {
-        buf.push_str("#");
+  fn rewrite(&mut self, f: &dyn Fn(Gc) -> Gc) {
+    match self {
+      &mut Step::Eval(ref mut value, ref mut env) | &mut Step::Exec(ref mut value, ref mut env) => {
+        *value = f(*value);
+        *env = f(*env);
       }
-      Object::Bool(value) => {
-        if value {
-          buf.push_str("#t");
-        } else {
-          buf.push_str("#f");
-        }
+      &mut Step::Apply(ref mut proc, ref mut value, ref mut env) => {
+        *proc = f(*proc);
+        *value = f(*value);
+        *env = f(*env);
       }
-      Object::Symbol(ref value) => {
-        buf.push_str(&value.0);
+      &mut Step::Run(ref mut code, ref mut env) => {
+        *code = code.rewrite(f);
+        *env = f(*env);
       }
-      Object::Pair(ref value) => {
-        if !value.is_list {
-          buf.push('(');
-          self.show(value.fst, buf)?;
-          buf.push_str(" * ");
-          self.show(value.snd, buf)?;
-          buf.push(')');
-        } else {
-          buf.push('(');
-          let mut xs = pointer;
-          while let Object::Pair(ref value) = self.heap.get(xs)? {
-            self.show(value.fst, buf)?;
-            if !self.heap.get(value.snd)?.is_unit() {
-              buf.push(' ');
+      &mut Step::Return(ref mut value) => {
+        *value = f(*value);
+      }
+    }
+  }
+}
+
+/// Where an evaluation `V0::debug_eval` started has got to.
+pub enum DebugStep {
+  /// It's finished, with this value.
+  Done(Gc),
+  /// It's paused between two steps. `result` is what the last one
+  /// returned, or unit if it didn't return anything, and `next_value`
+  /// and `next_env` are what the next one works on, which
+  /// `description` says in words. `pause` tells this pause apart from
+  /// every other, so only the latest can be continued.
+  Step { result: Gc, next_value: Gc, next_env: Gc, description: String, pause: usize },
+}
+
+/// The evaluation a `DebugStep::Step` stands for: the step it's paused
+/// before, the run its frames on the stack belong to, and the latest
+/// pause it handed out.
+struct Debugger {
+  step: Step,
+  id: usize,
+  base: usize,
+  pause: usize,
+}
+
+fn combiner(proc: Gc, lisp: &V0) -> Result<Gc> {
+  match lisp.heap.get(proc)? {
+    Object::Proc(Proc::App(ref app)) => {
+      return Ok(app.0);
+    }
+    Object::Proc(_) => {
+      return Ok(proc);
+    }
+    _ => {
+      return Err(Error::Type);
+    }
+  }
+}
+
+/// Where a binding lives: a cell in a frame alist, or a slot.
+#[derive(Clone, Copy)]
+enum Place {
+  Cell(Gc),
+  Slot(Gc, usize),
+}
+
+/// The name a call site's operator had, where it was found and from
+/// which environment, and the version that name's bindings were at
+/// then; see `operator`.
+struct CallSite {
+  name: Rc<str>,
+  env: Gc,
+  place: Place,
+  version: Rc<Cell<u64>>,
+  seen: u64,
+}
+
+/// With the lookup cache on, a miss in `env`'s own frame is resolved
+/// once per parent environment and remembered, so a closure body finds
+/// its free variables without walking the chain on every call. The
+/// cache holds the place rather than the value, since `define`
+/// rebinds in place; adding or removing a binding drops every entry
+/// for that name, and a collection drops them all.
+fn lookup(name: &Rc<str>, env: Gc, lisp: &mut V0) -> Result<Gc> {
+  if lisp.lookup_cache.is_none() {
+    return find(name, env, lisp)?.ok_or_else(|| Error::Unbound(name.clone()));
+  }
+  let value = match lisp.heap.get(env)? {
+    Object::Env(value) => value,
+    _ => return Err(Error::Type),
+  };
+  if let Some(place) = frame_place(name, env, &value, lisp)? {
+    return place_value(place, lisp);
+  }
+  let parent = match value.parent {
+    Some(parent) => parent,
+    None => return find(name, env, lisp)?.ok_or_else(|| Error::Unbound(name.clone())),
+  };
+  let cached = lisp.lookup_cache.as_ref()
+    .and_then(|cache| cache.get(name))
+    .and_then(|places| places.get(&parent))
+    .cloned();
+  if let Some(place) = cached {
+    return place_value(place, lisp);
+  }
+  let place = find_place(name, parent, lisp)?.ok_or_else(|| Error::Unbound(name.clone()))?;
+  if let Some(ref mut cache) = lisp.lookup_cache {
+    cache.entry(name.clone()).or_default().insert(parent, place);
+  }
+  return place_value(place, lisp);
+}
+
+/// The environment, `env` or one of its parents, whose own frame binds
+/// `name`.
+fn binding_env(name: &str, env: Gc, lisp: &V0) -> Result<Option<Gc>> {
+  let mut env = Some(env);
+  while let Some(pointer) = env {
+    let value = match lisp.heap.get(pointer)? {
+      Object::Env(value) => value,
+      _ => return Err(Error::Type),
+    };
+    if frame_place(name, pointer, &value, lisp)?.is_some() {
+      return Ok(Some(pointer));
+    }
+    env = value.parent;
+  }
+  return Ok(None);
+}
+
+/// Like `lookup`, but a miss is `None` instead of an error.
+fn find(name: &str, env: Gc, lisp: &V0) -> Result<Option<Gc>> {
+  match find_place(name, env, lisp)? {
+    Some(place) => return Ok(Some(place_value(place, lisp)?)),
+    None => return Ok(None),
+  }
+}
+
+/// Where `name` is bound, searching from `env` out, or for a name a
+/// macro introduced, where the macro was defined.
+fn find_place(name: &str, env: Gc, lisp: &V0) -> Result<Option<Place>> {
+  let mut env = Some(env);
+  while let Some(pointer) = env {
+    let value = match lisp.heap.get(pointer)? {
+      Object::Env(value) => value,
+      _ => return Err(Error::Type),
+    };
+    if let Some(place) = frame_place(name, pointer, &value, lisp)? {
+      return Ok(Some(place));
+    }
+    env = value.parent;
+  }
+  if let Some(alias) = lisp.aliases.get(name) {
+    return find_place(&alias.name, alias.env, lisp);
+  }
+  return Ok(None);
+}
+
+/// Where `name` is bound in `value`, the environment at `env`, not
+/// counting its parents.
+fn frame_place(name: &str, env: Gc, value: &Env, lisp: &V0) -> Result<Option<Place>> {
+  if let Some(ref slots) = value.slots {
+    if let Some(offset) = slots.offset(name) {
+      if slots.values.borrow()[offset].is_some() {
+        return Ok(Some(Place::Slot(env, offset)));
+      }
+    }
+  }
+  let mut xs = value.frame;
+  while let Object::Pair(ref cell) = lisp.heap.get(xs)? {
+    if let Object::Pair(ref binding) = lisp.heap.get(cell.fst)? {
+      if let Object::Symbol(ref symbol) = lisp.heap.get(binding.fst)? {
+        if &*symbol.0 == name {
+          return Ok(Some(Place::Cell(xs)));
+        }
+      }
+    }
+    xs = cell.snd;
+  }
+  return Ok(None);
+}
+
+fn place_value(place: Place, lisp: &V0) -> Result<Gc> {
+  match place {
+    Place::Cell(cell) => match lisp.heap.get(cell)? {
+      Object::Pair(ref cell) => match lisp.heap.get(cell.fst)? {
+        Object::Pair(ref binding) => return Ok(binding.snd),
+        _ => return Err(Error::Type),
+      },
+      _ => return Err(Error::Type),
+    },
+    Place::Slot(env, offset) => match lisp.heap.get(env)? {
+      Object::Env(Env { slots: Some(ref slots), .. }) => {
+        return slots.values.borrow()[offset].ok_or(Error::Type);
+      }
+      _ => return Err(Error::Type),
+    },
+  }
+}
+
+fn define(env: Gc, name: Gc, value: Gc, lisp: &mut V0) -> Result<()> {
+  let key = match lisp.heap.get(name)? {
+    Object::Symbol(symbol) => symbol.0,
+    _ => return Err(Error::Type),
+  };
+  let frame = match lisp.heap.get(env)? {
+    Object::Env(value) => value,
+    _ => return Err(Error::Type),
+  };
+  lisp.rebind(&key);
+  if let Some(ref slots) = frame.slots {
+    if let Some(offset) = slots.offset(&key) {
+      let old = slots.values.borrow()[offset];
+      match old {
+        Some(old) => lisp.heap.shade(old),
+        None => lisp.forget(&key),
+      }
+      slots.values.borrow_mut()[offset] = Some(value);
+      return Ok(());
+    }
+  }
+  let mut xs = frame.frame;
+  while let Object::Pair(ref cell) = lisp.heap.get(xs)? {
+    if let Object::Pair(ref binding) = lisp.heap.get(cell.fst)? {
+      if let Object::Symbol(ref symbol) = lisp.heap.get(binding.fst)? {
+        if symbol.0 == key {
+          let binding = lisp.pair(name, value)?;
+          let cell = Pair { fst: binding, snd: cell.snd, is_list: cell.is_list };
+          return lisp.heap.set(xs, Object::Pair(cell));
+        }
+      }
+    }
+    xs = cell.snd;
+  }
+  lisp.forget(&key);
+  let binding = lisp.pair(name, value)?;
+  let head = lisp.pair(binding, frame.frame)?;
+  let frame = Env { frame: head, ..frame };
+  return lisp.heap.set(env, Object::Env(frame));
+}
+
+/// Remove `name`'s binding from `env`'s own frame, leaving parents
+/// alone, and say whether there was one.
+fn undefine(env: Gc, name: &str, lisp: &mut V0) -> Result<bool> {
+  let frame = match lisp.heap.get(env)? {
+    Object::Env(value) => value,
+    _ => return Err(Error::Type),
+  };
+  lisp.rebind(name);
+  if let Some(ref slots) = frame.slots {
+    if let Some(offset) = slots.offset(name) {
+      let old = slots.values.borrow_mut()[offset].take();
+      if let Some(old) = old {
+        lisp.heap.shade(old);
+        lisp.forget(name);
+        return Ok(true);
+      }
+      return Ok(false);
+    }
+  }
+  let mut prev: Option<(Gc, Pair)> = None;
+  let mut xs = frame.frame;
+  while let Object::Pair(cell) = lisp.heap.get(xs)? {
+    if let Object::Pair(ref binding) = lisp.heap.get(cell.fst)? {
+      if let Object::Symbol(ref symbol) = lisp.heap.get(binding.fst)? {
+        if &*symbol.0 == name {
+          lisp.forget(name);
+          match prev {
+            Some((pointer, prev)) => {
+              let prev = Pair { fst: prev.fst, snd: cell.snd, is_list: prev.is_list };
+              lisp.heap.set(pointer, Object::Pair(prev))?;
+            }
+            None => {
+              let frame = Env { frame: cell.snd, ..frame };
+              lisp.heap.set(env, Object::Env(frame))?;
             }
-            xs = value.snd;
           }
-          guard(self.heap.get(xs)?.is_unit())?;
-          buf.push(')');
+          return Ok(true);
         }
       }
-      Object::Proc(_) => {
-        buf.push_str("<procedure>");
+    }
+    let next = cell.snd;
+    prev = Some((xs, cell));
+    xs = next;
+  }
+  return Ok(false);
+}
+
+/// The names a call to an operative with this parameter tree and
+/// dynamic name binds, if that's a plain symbol or a proper list of
+/// distinct symbols; anything fancier is bound the general way.
+fn slot_names(head: Gc, dynamic: Gc, lisp: &V0) -> Result<Option<Rc<[Rc<str>]>>> {
+  let mut names: Vec<Rc<str>> = vec![];
+  match lisp.heap.get(head)? {
+    Object::Symbol(symbol) => names.push(symbol.0),
+    Object::Unit | Object::Pair(_) => {
+      for x in lisp.list_iter(head) {
+        match lisp.heap.get(x?)? {
+          Object::Symbol(symbol) => names.push(symbol.0),
+          _ => return Ok(None),
+        }
       }
     }
-    return Ok(());
+    _ => return Ok(None),
+  }
+  if let Object::Symbol(symbol) = lisp.heap.get(dynamic)? {
+    names.push(symbol.0);
+  }
+  for (index, name) in names.iter().enumerate() {
+    if names[..index].contains(name) {
+      return Ok(None);
+    }
+  }
+  return Ok(Some(Rc::from(names)));
+}
+
+/// The environment for a call to `abs` with `value` as operands from
+/// `env`, as a slot frame, or `None` if it has to be bound the general
+/// way (which also reports any mismatch).
+fn slot_env(abs: &Abs, value: Gc, env: Gc, lisp: &mut V0) -> Result<Option<Gc>> {
+  let names = match abs.names {
+    Some(ref names) => names.clone(),
+    None => return Ok(None),
+  };
+  let mut values = vec![];
+  if let Object::Symbol(_) = lisp.heap.get(abs.head)? {
+    values.push(Some(value));
+  } else {
+    for x in lisp.list_iter(value) {
+      match x {
+        Ok(x) => values.push(Some(x)),
+        Err(_) => return Ok(None),
+      }
+    }
+  }
+  if let Object::Symbol(_) = lisp.heap.get(abs.dynamic)? {
+    values.push(Some(env));
+  }
+  if values.len() != names.len() {
+    return Ok(None);
+  }
+  let frame = lisp.unit()?;
+  let slots = Slots { names: names, values: Rc::new(RefCell::new(values)) };
+  let local = Env { frame: frame, parent: Some(abs.lexical), slots: Some(slots) };
+  return Ok(Some(lisp.heap.put(Object::Env(local))?));
+}
+
+fn bind(env: Gc, head: Gc, value: Gc, lisp: &mut V0) -> Result<()> {
+  match lisp.heap.get(head)? {
+    Object::Symbol(_) => {
+      return define(env, head, value, lisp);
+    }
+    Object::Unit => {
+      if !lisp.heap.get(value)?.is_unit() {
+        return Err(Error::Type);
+      }
+      return Ok(());
+    }
+    Object::Pair(ref tree) => {
+      match lisp.heap.get(value)? {
+        Object::Pair(ref data) => {
+          bind(env, tree.fst, data.fst, lisp)?;
+          return bind(env, tree.snd, data.snd, lisp);
+        }
+        _ => {
+          return Err(Error::Type);
+        }
+      }
+    }
+    _ => {
+      return Err(Error::Type);
+    }
+  }
+}
+
+fn arity(args: &[Gc], expected: usize) -> Result<()> {
+  if args.len() != expected {
+    return Err(Error::Arity { expected: expected, got: args.len() });
+  }
+  return Ok(());
+}
+
+/// `numer/denom` in lowest terms with a positive denominator, or
+/// `None` for a zero denominator or a result too big to hold.
+fn reduce(numer: i128, denom: i128) -> Option<(i64, i64)> {
+  if denom == 0 {
+    return None;
+  }
+  let (mut a, mut b) = (numer.abs(), denom.abs());
+  while b != 0 {
+    let rem = a % b;
+    a = b;
+    b = rem;
+  }
+  let sign = denom.signum();
+  let numer = i64::try_from(numer / a * sign).ok()?;
+  let denom = i64::try_from(denom / a * sign).ok()?;
+  return Some((numer, denom));
+}
+
+/// The result as a ratio, see `V0::rational`, or `None` if it takes
+/// more than an `i64`; see `big_arith`.
+fn arith(nat: &Nat, args: &[Gc], lisp: &V0) -> Result<Option<(i64, i64)>> {
+  let mut xs = Vec::with_capacity(args.len());
+  for arg in args.iter() {
+    match lisp.fraction(*arg) {
+      Ok(x) => xs.push(x),
+      Err(_) => return Ok(None),
+    }
+  }
+  let add = |(a, b): (i64, i64), (c, d): (i64, i64)| {
+    reduce(a as i128 * d as i128 + c as i128 * b as i128, b as i128 * d as i128)
+  };
+  let sub = |(a, b): (i64, i64), (c, d): (i64, i64)| {
+    reduce(a as i128 * d as i128 - c as i128 * b as i128, b as i128 * d as i128)
+  };
+  let mul = |(a, b): (i64, i64), (c, d): (i64, i64)| {
+    reduce(a as i128 * c as i128, b as i128 * d as i128)
+  };
+  let div = |(a, b): (i64, i64), (c, d): (i64, i64)| {
+    reduce(a as i128 * d as i128, b as i128 * c as i128)
+  };
+  let result = match *nat {
+    Nat::Add => {
+      xs.iter().try_fold((0, 1), |acc, x| add(acc, *x))
+    }
+    Nat::Mul => {
+      xs.iter().try_fold((1, 1), |acc, x| mul(acc, *x))
+    }
+    Nat::Sub => {
+      match xs.len() {
+        0 => return Err(Error::Arity { expected: 1, got: 0 }),
+        1 => sub((0, 1), xs[0]),
+        _ => xs[1..].iter().try_fold(xs[0], |acc, x| sub(acc, *x)),
+      }
+    }
+    Nat::Div => {
+      match xs.len() {
+        0 => return Err(Error::Arity { expected: 1, got: 0 }),
+        1 => div((1, 1), xs[0]),
+        _ => xs[1..].iter().try_fold(xs[0], |acc, x| div(acc, *x)),
+      }
+    }
+    Nat::Rem => {
+      arity(args, 2)?;
+      if xs[0].1 != 1 || xs[1].1 != 1 {
+        return Err(Error::Type);
+      }
+      xs[0].0.checked_rem(xs[1].0).map(|rem| (rem, 1))
+    }
+    Nat::Neg => {
+      arity(args, 1)?;
+      sub((0, 1), xs[0])
+    }
+    Nat::Abs_ => {
+      arity(args, 1)?;
+      reduce((xs[0].0 as i128).abs(), xs[0].1 as i128)
+    }
+    _ => {
+      return Err(Error::Type);
+    }
+  };
+  return Ok(result);
+}
+
+/// `arith` again, for when it overflows or is given a big number.
+/// Zero denominators carry through to the end, which rejects them.
+#[cfg(feature = "bignum")]
+fn big_arith(nat: &Nat, args: &[Gc], lisp: &mut V0) -> Result<Gc> {
+  let mut xs = Vec::with_capacity(args.len());
+  for arg in args.iter() {
+    xs.push(lisp.big_fraction(*arg)?);
+  }
+  let add = |(a, b): (BigInt, BigInt), (c, d): &(BigInt, BigInt)| (a * d + c * &b, b * d);
+  let sub = |(a, b): (BigInt, BigInt), (c, d): &(BigInt, BigInt)| (a * d - c * &b, b * d);
+  let mul = |(a, b): (BigInt, BigInt), (c, d): &(BigInt, BigInt)| (a * c, b * d);
+  let div = |(a, b): (BigInt, BigInt), (c, d): &(BigInt, BigInt)| (a * d, b * c);
+  let zero = (BigInt::zero(), BigInt::one());
+  let one = (BigInt::one(), BigInt::one());
+  let (numer, denom) = match *nat {
+    Nat::Add => {
+      xs.iter().fold(zero, add)
+    }
+    Nat::Mul => {
+      xs.iter().fold(one, mul)
+    }
+    Nat::Sub => {
+      match xs.len() {
+        0 => return Err(Error::Arity { expected: 1, got: 0 }),
+        1 => sub(zero, &xs[0]),
+        _ => xs[1..].iter().fold(xs[0].clone(), sub),
+      }
+    }
+    Nat::Div => {
+      match xs.len() {
+        0 => return Err(Error::Arity { expected: 1, got: 0 }),
+        1 => div(one, &xs[0]),
+        _ => xs[1..].iter().fold(xs[0].clone(), div),
+      }
+    }
+    Nat::Rem => {
+      arity(args, 2)?;
+      if !xs[0].1.is_one() || !xs[1].1.is_one() || xs[1].0.is_zero() {
+        return Err(Error::Type);
+      }
+      (&xs[0].0 % &xs[1].0, BigInt::one())
+    }
+    Nat::Neg => {
+      arity(args, 1)?;
+      sub(zero, &xs[0])
+    }
+    Nat::Abs_ => {
+      arity(args, 1)?;
+      (xs[0].0.abs(), xs[0].1.clone())
+    }
+    _ => {
+      return Err(Error::Type);
+    }
+  };
+  return lisp.big_rational(numer, denom);
+}
+
+/// Without `bignum`, anything `arith` can't do is a type error.
+#[cfg(not(feature = "bignum"))]
+fn big_arith(_: &Nat, _: &[Gc], _: &mut V0) -> Result<Gc> {
+  return Err(Error::Type);
+}
+
+/// Whether `args` are in the order `nat` says, or `None` if one of
+/// them is too big to tell; see `big_compare`.
+fn compare(nat: &Nat, args: &[Gc], lisp: &V0) -> Result<Option<bool>> {
+  if args.len() < 2 {
+    return Err(Error::Arity { expected: 2, got: args.len() });
+  }
+  let mut xs = Vec::with_capacity(args.len());
+  for arg in args.iter() {
+    match lisp.fraction(*arg) {
+      Ok(x) => xs.push(x),
+      Err(_) => return Ok(None),
+    }
+  }
+  for pair in xs.windows(2) {
+    let ((a, b), (c, d)) = (pair[0], pair[1]);
+    let flag = in_order(nat, a as i128 * d as i128, c as i128 * b as i128)?;
+    if !flag {
+      return Ok(Some(false));
+    }
+  }
+  return Ok(Some(true));
+}
+
+fn in_order<T: PartialOrd>(nat: &Nat, x: T, y: T) -> Result<bool> {
+  let flag = match *nat {
+    Nat::NumEq => x == y,
+    Nat::NumLt => x < y,
+    Nat::NumGt => x > y,
+    Nat::NumLe => x <= y,
+    Nat::NumGe => x >= y,
+    _ => return Err(Error::Type),
+  };
+  return Ok(flag);
+}
+
+#[cfg(feature = "bignum")]
+fn big_compare(nat: &Nat, args: &[Gc], lisp: &V0) -> Result<bool> {
+  let mut xs = Vec::with_capacity(args.len());
+  for arg in args.iter() {
+    xs.push(lisp.big_fraction(*arg)?);
+  }
+  for pair in xs.windows(2) {
+    let ((a, b), (c, d)) = (&pair[0], &pair[1]);
+    if !in_order(nat, a * d, c * b)? {
+      return Ok(false);
+    }
+  }
+  return Ok(true);
+}
+
+#[cfg(not(feature = "bignum"))]
+fn big_compare(_: &Nat, _: &[Gc], _: &V0) -> Result<bool> {
+  return Err(Error::Type);
+}
+
+/// Unit and the booleans are allocated afresh each time they're built,
+/// so they compare by value; everything else compares by slot.
+fn is_eq(a: Gc, b: Gc, lisp: &V0) -> Result<bool> {
+  if a == b {
+    return Ok(true);
+  }
+  match (lisp.heap.get(a)?, lisp.heap.get(b)?) {
+    (Object::Unit, Object::Unit) => {
+      return Ok(true);
+    }
+    (Object::Bool(x), Object::Bool(y)) => {
+      return Ok(x == y);
+    }
+    _ => {
+      return Ok(false);
+    }
+  }
+}
+
+fn is_eqv(a: Gc, b: Gc, lisp: &V0) -> Result<bool> {
+  match (lisp.heap.get(a)?, lisp.heap.get(b)?) {
+    (Object::Number(x), Object::Number(y)) => {
+      return Ok(x == y);
+    }
+    (Object::Char(x), Object::Char(y)) => {
+      return Ok(x == y);
+    }
+    (Object::Rational { numer: a, denom: b }, Object::Rational { numer: c, denom: d }) => {
+      return Ok(a == c && b == d);
+    }
+    #[cfg(feature = "bignum")]
+    (Object::BigInt(x), Object::BigInt(y)) => {
+      return Ok(x == y);
+    }
+    #[cfg(feature = "bignum")]
+    (Object::BigRational { numer: a, denom: b }, Object::BigRational { numer: c, denom: d }) => {
+      return Ok(a == c && b == d);
+    }
+    _ => {
+      return is_eq(a, b, lisp);
+    }
+  }
+}
+
+/// Pairs already on the worklist are assumed equal, which is what
+/// lets cyclic structure terminate.
+fn is_equal(a: Gc, b: Gc, lisp: &V0) -> Result<bool> {
+  let mut seen = HashSet::new();
+  let mut todo = vec![(a, b)];
+  while let Some((a, b)) = todo.pop() {
+    if a == b || !seen.insert((a, b)) {
+      continue;
+    }
+    match (lisp.heap.get(a)?, lisp.heap.get(b)?) {
+      (Object::Pair(ref x), Object::Pair(ref y)) => {
+        todo.push((x.snd, y.snd));
+        todo.push((x.fst, y.fst));
+      }
+      (Object::Vector(ref x), Object::Vector(ref y)) => {
+        let (x, y) = (x.borrow(), y.borrow());
+        if x.len() != y.len() {
+          return Ok(false);
+        }
+        todo.extend(x.iter().cloned().zip(y.iter().cloned()).rev());
+      }
+      (Object::Symbol(ref x), Object::Symbol(ref y)) => {
+        if x.0 != y.0 {
+          return Ok(false);
+        }
+      }
+      (Object::Str(ref x), Object::Str(ref y)) => {
+        if x != y {
+          return Ok(false);
+        }
+      }
+      (Object::Bytevector(ref x), Object::Bytevector(ref y)) => {
+        if x != y {
+          return Ok(false);
+        }
+      }
+      (Object::Foreign(ref x), Object::Foreign(ref y)) if x.name == y.name && lisp.equalities.contains_key(&x.name) => {
+        if !lisp.equalities[&x.name](&*x.value, &*y.value) {
+          return Ok(false);
+        }
+      }
+      _ => {
+        if !is_eqv(a, b, lisp)? {
+          return Ok(false);
+        }
+      }
+    }
+  }
+  return Ok(true);
+}
+
+/// Describe the first place where `a` and `b` differ, or `None` if
+/// they're structurally equal. The path counts list elements, so
+/// "at 2.0" is the first element of the third element, and "3.." is the
+/// tail after three elements. Vectors are walked by index like lists.
+fn diff(a: Gc, b: Gc, lisp: &V0) -> Result<Option<String>> {
+  let mut seen = HashSet::new();
+  let mut todo = vec![(a, b, vec![], 0)];
+  while let Some((a, b, path, index)) = todo.pop() {
+    if !seen.insert((a, b)) {
+      continue;
+    }
+    match (lisp.heap.get(a)?, lisp.heap.get(b)?) {
+      (Object::Pair(ref x), Object::Pair(ref y)) => {
+        let mut head = path.clone();
+        head.push(index.to_string());
+        todo.push((x.snd, y.snd, path, index + 1));
+        todo.push((x.fst, y.fst, head, 0));
+        continue;
+      }
+      (Object::Vector(ref x), Object::Vector(ref y)) if x.borrow().len() == y.borrow().len() => {
+        for (i, (&x, &y)) in x.borrow().iter().zip(y.borrow().iter()).enumerate().rev() {
+          let mut path = path.clone();
+          path.push(i.to_string());
+          todo.push((x, y, path, 0));
+        }
+        continue;
+      }
+      (Object::Vector(_), Object::Vector(_)) => {}
+      _ => {
+        if is_equal(a, b, lisp)? {
+          continue;
+        }
+      }
+    }
+    let mut path = path;
+    if index > 0 {
+      path.push(format!("{}..", index));
+    }
+    let mut buf = String::new();
+    if path.is_empty() {
+      buf.push_str("at top: ");
+    } else {
+      buf.push_str("at ");
+      buf.push_str(&path.join("."));
+      buf.push_str(": ");
+    }
+    lisp.show(a, &mut buf)?;
+    buf.push_str(" vs ");
+    lisp.show(b, &mut buf)?;
+    return Ok(Some(buf));
+  }
+  return Ok(None);
+}
+
+/// Rebuild the form that would construct `proc`: ($vau head dynamic
+/// . body) for an operative, ($lambda head . body) for an applicative
+/// over one that ignores its dynamic environment, and (wrap ...) around
+/// the operative otherwise. Primitives and continuations have no source.
+fn procedure_source(proc: Gc, lisp: &mut V0) -> Result<Option<Gc>> {
+  match lisp.heap.get(proc)? {
+    Object::Proc(Proc::Abs(ref abs)) => {
+      let dynamic = lisp.pair(abs.dynamic, abs.tail)?;
+      let head = lisp.pair(abs.head, dynamic)?;
+      let name = lisp.symbol(Rc::from("$vau"))?;
+      let source = lisp.pair(name, head)?;
+      return Ok(Some(source));
+    }
+    Object::Proc(Proc::App(ref app)) => {
+      if let Object::Proc(Proc::Abs(ref abs)) = lisp.heap.get(app.0)? {
+        if !lisp.heap.get(abs.dynamic)?.is_unit() {
+          let inner = procedure_source(app.0, lisp)?.unwrap();
+          let name = lisp.symbol(Rc::from("wrap"))?;
+          let source = lisp.list(&[name, inner])?;
+          return Ok(Some(source));
+        }
+        let head = lisp.pair(abs.head, abs.tail)?;
+        let name = lisp.symbol(Rc::from("$lambda"))?;
+        let source = lisp.pair(name, head)?;
+        return Ok(Some(source));
+      }
+      return Ok(None);
+    }
+    Object::Proc(_) => {
+      return Ok(None);
+    }
+    _ => {
+      return Err(Error::Type);
+    }
+  }
+}
+
+fn eval(
+  value: Gc,
+  env: Gc,
+  lisp: &mut V0) -> Result<Step> {
+  if let Some(ref hook) = lisp.trace_hook {
+    hook(value, lisp);
+  }
+  lisp.steps += 1;
+  spend(lisp)?;
+  match lisp.heap.get(value)? {
+    Object::Symbol(ref symbol) => {
+      return variable(&symbol.0, env, lisp);
+    }
+    Object::Pair(ref pair) => {
+      if let Object::Symbol(ref symbol) = lisp.heap.get(pair.fst)? {
+        count_lookup(&symbol.0, lisp);
+        let proc = operator(value, &symbol.0, env, lisp)?;
+        return Ok(Step::Apply(proc, pair.snd, env));
+      }
+      lisp.stack.push(Frame::Combine { operands: pair.snd, env: env });
+      return Ok(Step::Eval(pair.fst, env));
+    }
+    _ => {
+      return Ok(Step::Return(value));
+    }
+  }
+}
+
+/// Look up `name`, the operator of the combination `site`, in `env`.
+/// Each call site remembers where its operator was found last, and from
+/// which environment: `env` itself, or its parent when `env` doesn't
+/// bind the name, which is what a closure body called over and over
+/// sees. The entry holds on to the name's binding version, so it's good
+/// until something binds or unbinds that name somewhere.
+fn operator(site: Gc, name: &Rc<str>, env: Gc, lisp: &mut V0) -> Result<Gc> {
+  let value = match lisp.heap.get(env)? {
+    Object::Env(value) => value,
+    _ => return Err(Error::Type),
+  };
+  let cached = match lisp.call_cache.as_ref().and_then(|cache| cache.get(&site)) {
+    Some(entry) if entry.version.get() == entry.seen && entry.name == *name => {
+      Some((entry.env, entry.place))
+    }
+    _ => None,
+  };
+  if let Some((from, place)) = cached {
+    if from == env {
+      return place_value(place, lisp);
+    }
+    if Some(from) == value.parent && frame_place(name, env, &value, lisp)?.is_none() {
+      return place_value(place, lisp);
+    }
+  }
+  let (from, place) = match frame_place(name, env, &value, lisp)? {
+    Some(place) => (env, place),
+    None => {
+      let from = value.parent.unwrap_or(env);
+      let place = find_place(name, from, lisp)?.ok_or_else(|| Error::Unbound(name.clone()))?;
+      (from, place)
+    }
+  };
+  if let Some(ref mut cache) = lisp.call_cache {
+    let version = lisp.versions.entry(name.clone()).or_default().clone();
+    let seen = version.get();
+    let entry = CallSite { name: name.clone(), env: from, place: place, version: version, seen: seen };
+    cache.insert(site, entry);
+  }
+  return place_value(place, lisp);
+}
+
+fn count_lookup(name: &str, lisp: &mut V0) {
+  if !lisp.lookup_counts.is_empty() {
+    if let Some(count) = lisp.lookup_counts.get_mut(name) {
+      *count += 1;
+    }
+  }
+}
+
+/// `count_lookup` for a variable compiled from the symbol `form`.
+fn count_form_lookup(form: Gc, lisp: &mut V0) -> Result<()> {
+  if !lisp.lookup_counts.is_empty() {
+    if let Object::Symbol(symbol) = lisp.heap.get(form)? {
+      count_lookup(&symbol.0, lisp);
+    }
   }
+  return Ok(());
+}
+
+fn variable(name: &Rc<str>, env: Gc, lisp: &mut V0) -> Result<Step> {
+  count_lookup(name, lisp);
+  let value = lookup(name, env, lisp)?;
+  return Ok(Step::Return(value));
+}
+
+/// Compile `value` for evaluation in `env`; see `Code`. The name of
+/// every operator looked up along the way goes in `compile_names`, and
+/// from then on binding or unbinding that name in any environment moves
+/// `compile_epoch` on, which puts everything compiled before out of date.
+/// A variable resolved to its address goes in `compile_vars`, where
+/// only a binding added or removed does that, since the address of one
+/// changed in place stays good.
+fn compile(value: Gc, env: Gc, lisp: &mut V0) -> Result<Rc<Code>> {
+  let pair = match lisp.heap.get(value)? {
+    Object::Symbol(symbol) => return address(value, symbol.0, env, lisp),
+    Object::Pair(pair) => pair,
+    _ => return Ok(Rc::new(Code::Const(value))),
+  };
+  let name = match lisp.heap.get(pair.fst)? {
+    Object::Symbol(symbol) => symbol.0,
+    _ => {
+      let op = compile(pair.fst, env, lisp)?;
+      return Ok(Rc::new(Code::Combine(op, pair.snd)));
+    }
+  };
+  let proc = match find(&name, env, lisp)? {
+    Some(proc) => proc,
+    None => return Ok(Rc::new(Code::Combine(Rc::new(Code::Var(name)), pair.snd))),
+  };
+  lisp.compile_names.insert(name);
+  let source = Source { form: value, epoch: lisp.compile_epoch };
+  let code = match (lisp.heap.get(proc)?, lisp.args(pair.snd)) {
+    (Object::Proc(Proc::Nat(Nat::Quote)), Ok(ref args)) if args.len() == 1 => {
+      Code::Quote(source, args[0])
+    }
+    (Object::Proc(Proc::Nat(Nat::If)), Ok(ref args)) if args.len() == 2 || args.len() == 3 => {
+      let test = compile(args[0], env, lisp)?;
+      let conseq = compile(args[1], env, lisp)?;
+      let alt = match args.get(2) {
+        Some(&alt) => compile(alt, env, lisp)?,
+        None => Rc::new(Code::Const(lisp.unit()?)),
+      };
+      Code::If(source, test, conseq, alt)
+    }
+    (Object::Proc(Proc::Nat(Nat::Sequence)), Ok(ref args)) => {
+      let body = compile_all(args, env, lisp)?;
+      Code::Seq(source, body)
+    }
+    (Object::Proc(Proc::Nat(Nat::Define)), Ok(ref args)) if args.len() == 2 => {
+      let value = compile(args[1], env, lisp)?;
+      Code::Define(source, args[0], value)
+    }
+    (Object::Proc(Proc::App(ref app)), Ok(ref args)) => {
+      let args = compile_all(args, env, lisp)?;
+      Code::Call(source, app.0, args)
+    }
+    _ => Code::Operate(source, proc, pair.snd),
+  };
+  return Ok(Rc::new(code));
 }
 
-pub fn init(capacity: usize) -> impl super::Lisp<Error=Error> {
-  V0 {
-    heap: Heap::with_capacity(capacity),
+/// The variable `symbol`, named `name`, resolved against `env` to where
+/// it's bound.
+fn address(symbol: Gc, name: Rc<str>, env: Gc, lisp: &mut V0) -> Result<Rc<Code>> {
+  let source = Source { form: symbol, epoch: lisp.compile_epoch };
+  let mut depth = 0;
+  let mut next = Some(env);
+  while let Some(pointer) = next {
+    let value = match lisp.heap.get(pointer)? {
+      Object::Env(value) => value,
+      _ => return Err(Error::Type),
+    };
+    let code = match frame_place(&name, pointer, &value, lisp)? {
+      Some(Place::Slot(_, offset)) => Code::Local(source, depth, offset),
+      Some(Place::Cell(cell)) => Code::Cell(source, cell),
+      None => {
+        next = value.parent;
+        depth += 1;
+        continue;
+      }
+    };
+    lisp.compile_vars.insert(name);
+    return Ok(Rc::new(code));
+  }
+  return Ok(Rc::new(Code::Var(name)));
+}
+
+/// The value in slot `offset` of the environment `depth` parents out
+/// from `env`.
+fn local(depth: usize, offset: usize, env: Gc, lisp: &V0) -> Result<Gc> {
+  let mut env = env;
+  for _ in 0..depth {
+    env = match lisp.heap.get(env)? {
+      Object::Env(Env { parent: Some(parent), .. }) => parent,
+      _ => return Err(Error::Type),
+    };
+  }
+  return place_value(Place::Slot(env, offset), lisp);
+}
+
+fn compile_all(values: &[Gc], env: Gc, lisp: &mut V0) -> Result<Rc<[Rc<Code>]>> {
+  let mut codes = vec![];
+  for &value in values.iter() {
+    codes.push(compile(value, env, lisp)?);
+  }
+  return Ok(codes.into());
+}
+
+/// Take one step out of the budget, if there is one.
+fn spend(lisp: &mut V0) -> Result<()> {
+  if let Some(ref mut budget) = lisp.budget {
+    if *budget == 0 {
+      return Err(Error::Time);
+    }
+    *budget -= 1;
+  }
+  return Ok(());
+}
+
+/// Run one node of compiled code, as `eval` does a form.
+fn perform(code: &Code, env: Gc, lisp: &mut V0) -> Result<Step> {
+  if let Some(source) = code.source() {
+    if source.epoch != lisp.compile_epoch {
+      return Ok(Step::Eval(source.form, env));
+    }
+  }
+  lisp.steps += 1;
+  spend(lisp)?;
+  match code {
+    &Code::Const(value) | &Code::Quote(_, value) => {
+      return Ok(Step::Return(value));
+    }
+    Code::Var(name) => {
+      return variable(name, env, lisp);
+    }
+    &Code::Local(source, depth, offset) => {
+      count_form_lookup(source.form, lisp)?;
+      return Ok(Step::Return(local(depth, offset, env, lisp)?));
+    }
+    &Code::Cell(source, cell) => {
+      count_form_lookup(source.form, lisp)?;
+      return Ok(Step::Return(place_value(Place::Cell(cell), lisp)?));
+    }
+    &Code::Combine(ref op, operands) => {
+      lisp.stack.push(Frame::Combine { operands: operands, env: env });
+      return Ok(Step::Run(op.clone(), env));
+    }
+    Code::If(_, test, conseq, alt) => {
+      lisp.stack.push(Frame::Branch { conseq: conseq.clone(), alt: alt.clone(), env: env });
+      return Ok(Step::Run(test.clone(), env));
+    }
+    Code::Seq(_, body) => {
+      return sequence(body.clone(), 0, env, lisp);
+    }
+    &Code::Define(_, head, ref value) => {
+      lisp.stack.push(Frame::Define { head: head, env: env });
+      return Ok(Step::Run(value.clone(), env));
+    }
+    &Code::Call(_, proc, ref args) => {
+      let done = lisp.unit()?;
+      return arguments(proc, args.clone(), 0, done, env, lisp);
+    }
+    &Code::Operate(_, proc, operands) => {
+      return Ok(Step::Apply(proc, operands, env));
+    }
+  }
+}
+
+fn sequence(
+  body: Rc<[Rc<Code>]>,
+  next: usize,
+  env: Gc,
+  lisp: &mut V0) -> Result<Step> {
+  let code = match body.get(next) {
+    Some(code) => code.clone(),
+    None => return Ok(Step::Return(lisp.unit()?)),
+  };
+  if next + 1 < body.len() {
+    lisp.stack.push(Frame::Seq { body: body, next: next + 1, env: env });
+  }
+  return Ok(Step::Run(code, env));
+}
+
+fn arguments(
+  proc: Gc,
+  todo: Rc<[Rc<Code>]>,
+  next: usize,
+  done: Gc,
+  env: Gc,
+  lisp: &mut V0) -> Result<Step> {
+  let code = match todo.get(next) {
+    Some(code) => code.clone(),
+    None => {
+      let mut args = lisp.unit()?;
+      let mut xs = done;
+      while let Object::Pair(ref value) = lisp.heap.get(xs)? {
+        args = lisp.pair(value.fst, args)?;
+        xs = value.snd;
+      }
+      return Ok(Step::Apply(proc, args, env));
+    }
+  };
+  lisp.stack.push(Frame::Args { proc: proc, todo: todo, next: next + 1, done: done, env: env });
+  return Ok(Step::Run(code, env));
+}
+
+fn exec(
+  value: Gc,
+  env: Gc,
+  lisp: &mut V0) -> Result<Step> {
+  match lisp.heap.get(value)? {
+    Object::Unit => {
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    Object::Pair(ref pair) => {
+      if !lisp.heap.get(pair.snd)?.is_unit() {
+        lisp.stack.push(Frame::Exec { body: pair.snd, env: env });
+      }
+      return Ok(Step::Eval(pair.fst, env));
+    }
+    _ => {
+      return Err(Error::Type);
+    }
+  }
+}
+
+fn evlis(
+  proc: Gc,
+  todo: Gc,
+  done: Gc,
+  env: Gc,
+  lisp: &mut V0) -> Result<Step> {
+  match lisp.heap.get(todo)? {
+    Object::Unit => {
+      let mut args = lisp.unit()?;
+      let mut xs = done;
+      while let Object::Pair(ref value) = lisp.heap.get(xs)? {
+        args = lisp.pair(value.fst, args)?;
+        xs = value.snd;
+      }
+      return Ok(Step::Apply(proc, args, env));
+    }
+    Object::Pair(ref pair) => {
+      lisp.stack.push(Frame::Evlis { proc: proc, todo: pair.snd, done: done, env: env });
+      return Ok(Step::Eval(pair.fst, env));
+    }
+    _ => {
+      return Err(Error::Type);
+    }
+  }
+}
+
+fn apply(
+  proc: Gc,
+  value: Gc,
+  env: Gc,
+  lisp: &mut V0) -> Result<Step> {
+  match lisp.heap.get(proc)? {
+    Object::Proc(Proc::Nat(ref nat)) => {
+      return native(nat, value, env, lisp);
+    }
+    Object::Proc(Proc::App(ref app)) => {
+      let done = lisp.unit()?;
+      return evlis(app.0, value, done, env, lisp);
+    }
+    Object::Proc(Proc::Abs(ref abs)) => {
+      if let Some(local) = slot_env(abs, value, env, lisp)? {
+        return Ok(Step::Exec(abs.tail, local));
+      }
+      let local = lisp.env_new(Some(abs.lexical))?;
+      bind(local, abs.head, value, lisp)?;
+      if let Object::Symbol(_) = lisp.heap.get(abs.dynamic)? {
+        define(local, abs.dynamic, env, lisp)?;
+      }
+      return Ok(Step::Exec(abs.tail, local));
+    }
+    Object::Proc(Proc::Cont(ref cont)) => {
+      let args = lisp.args(value)?;
+      let value = match args.len() {
+        0 => lisp.unit()?,
+        1 => args[0],
+        got => return Err(Error::Arity { expected: 1, got: got }),
+      };
+      if let Some(extent) = cont.extent {
+        let live = match lisp.stack.get(cont.depth) {
+          Some(&Frame::Extent { id }) => id == extent,
+          _ => false,
+        };
+        guard(live)?;
+      }
+      guard(lisp.runs.iter().any(|run| run.id == cont.run))?;
+      let escape = Escape {
+        run: cont.run,
+        depth: cont.depth,
+        frames: cont.frames.clone(),
+        value: value,
+        winds: cont.winds.clone(),
+      };
+      return travel(Rc::new(escape), lisp);
+    }
+    Object::Macro(ref rules) => {
+      let form = transform(rules, value, lisp)?;
+      return Ok(Step::Eval(form, env));
+    }
+    Object::Proc(Proc::Traced(ref traced)) => {
+      let call = lisp.pair(traced.name, value)?;
+      let mut buf = String::new();
+      lisp.show(call, &mut buf)?;
+      writeln!(lisp.trace_writer, "; {}", buf).map_err(io_error)?;
+      return Ok(Step::Apply(traced.proc, value, env));
+    }
+    Object::Proc(Proc::Param(ref param)) => {
+      let args = lisp.args(value)?;
+      let binding = lisp.params.iter().rposition(|&(key, _)| key == proc);
+      match (args.len(), binding) {
+        (0, Some(index)) => {
+          return Ok(Step::Return(lisp.params[index].1));
+        }
+        (0, None) => {
+          return Ok(Step::Return(param.value));
+        }
+        (1, Some(index)) => {
+          lisp.params[index].1 = args[0];
+        }
+        (1, None) => {
+          lisp.heap.set(proc, Object::Proc(Proc::Param(Param { value: args[0] })))?;
+        }
+        (got, _) => {
+          return Err(Error::Arity { expected: 1, got: got });
+        }
+      }
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    Object::Proc(Proc::Compiled(ref compiled)) => {
+      arity(&lisp.args(value)?, 0)?;
+      if compiled.epoch == lisp.compile_epoch {
+        return Ok(Step::Run(compiled.code.clone(), compiled.env));
+      }
+      let code = compile(compiled.datum, compiled.env, lisp)?;
+      let compiled = Compiled { epoch: lisp.compile_epoch, code: code.clone(), ..compiled.clone() };
+      let env = compiled.env;
+      lisp.heap.set(proc, Object::Proc(Proc::Compiled(compiled)))?;
+      return Ok(Step::Run(code, env));
+    }
+    _ => {
+      return Err(Error::Type);
+    }
+  }
+}
+
+/// Run the `after` of each wind being left, innermost first, and the
+/// `before` of each being entered, outermost first, one at a time with
+/// a `Travel` frame to come back to, then go to `escape`. Unwinding
+/// out of a nested run happens in that run, before it's abandoned.
+fn travel(escape: Rc<Escape>, lisp: &mut V0) -> Result<Step> {
+  loop {
+    let common = lisp.winds.iter().zip(escape.winds.iter())
+      .take_while(|(from, to)| from.id == to.id)
+      .count();
+    if lisp.winds.len() > common {
+      let wind = lisp.unwind().unwrap();
+      if let Some(after) = wind.after {
+        lisp.stack.push(Frame::Travel { escape: escape, entering: false });
+        let unit = lisp.unit()?;
+        return Ok(Step::Apply(after, unit, wind.env));
+      }
+      continue;
+    }
+    let wind = match escape.winds.get(common) {
+      Some(wind) => wind.clone(),
+      None => break,
+    };
+    if let Some(before) = wind.before {
+      lisp.stack.push(Frame::Travel { escape: escape, entering: true });
+      let unit = lisp.unit()?;
+      return Ok(Step::Apply(before, unit, wind.env));
+    }
+    lisp.wind(wind);
+  }
+  if lisp.runs.last().unwrap().id == escape.run {
+    lisp.stack.truncate(escape.depth);
+    lisp.stack.extend(escape.frames.iter().cloned());
+    return Ok(Step::Return(escape.value));
+  }
+  lisp.escape = Some((*escape).clone());
+  return Err(Error::Escape);
+}
+
+fn resume(
+  frame: Frame,
+  value: Gc,
+  lisp: &mut V0) -> Result<Step> {
+  match frame {
+    Frame::Combine { operands, env } => {
+      return Ok(Step::Apply(value, operands, env));
+    }
+    Frame::Evlis { proc, todo, done, env } => {
+      let done = lisp.pair(value, done)?;
+      return evlis(proc, todo, done, env, lisp);
+    }
+    Frame::Exec { body, env } => {
+      return exec(body, env, lisp);
+    }
+    Frame::If { conseq, alt, env } => {
+      if lisp.is_true(value)? {
+        return Ok(Step::Eval(conseq, env));
+      }
+      if lisp.heap.get(alt)?.is_unit() {
+        return Ok(Step::Return(alt));
+      }
+      return Ok(Step::Eval(alt, env));
+    }
+    Frame::Define { head, env } => {
+      bind(env, head, value, lisp)?;
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    Frame::Extent { .. } => {
+      return Ok(Step::Return(value));
+    }
+    Frame::Winding { before, during, after, env } => {
+      let id = lisp.extent_id;
+      lisp.extent_id += 1;
+      let depth = lisp.stack.len();
+      lisp.wind(Wind {
+        id: id,
+        depth: depth,
+        before: Some(before),
+        after: Some(after),
+        env: env,
+        bindings: Rc::new(vec![]),
+      });
+      lisp.stack.push(Frame::Wound);
+      let unit = lisp.unit()?;
+      return Ok(Step::Apply(during, unit, env));
+    }
+    Frame::Parameterize { body, env } => {
+      let mut bindings = vec![];
+      for pair in lisp.args(value)?.chunks(2) {
+        let param = combiner(pair[0], lisp)?;
+        match lisp.heap.get(param)? {
+          Object::Proc(Proc::Param(_)) => {}
+          _ => return Err(Error::Type),
+        }
+        bindings.push((param, pair[1]));
+      }
+      let id = lisp.extent_id;
+      lisp.extent_id += 1;
+      let depth = lisp.stack.len();
+      lisp.wind(Wind { id: id, depth: depth, before: None, after: None, env: env, bindings: Rc::new(bindings) });
+      lisp.stack.push(Frame::Wound);
+      return Ok(Step::Exec(body, env));
+    }
+    Frame::Wound => {
+      let wind = lisp.unwind().ok_or(Error::Guard)?;
+      let after = match wind.after {
+        Some(after) => after,
+        None => return Ok(Step::Return(value)),
+      };
+      lisp.stack.push(Frame::Unwinding { value: value });
+      let unit = lisp.unit()?;
+      return Ok(Step::Apply(after, unit, wind.env));
+    }
+    Frame::Unwinding { value } => {
+      return Ok(Step::Return(value));
+    }
+    Frame::Travel { escape, entering } => {
+      if entering {
+        let wind = escape.winds[lisp.winds.len()].clone();
+        lisp.wind(wind);
+      }
+      return travel(escape, lisp);
+    }
+    Frame::DoBind { spec } => {
+      let env = lisp.env_new(Some(spec.env))?;
+      let vars = lisp.args(spec.vars)?;
+      for (var, value) in vars.into_iter().zip(lisp.args(value)?) {
+        define(env, var, value, lisp)?;
+      }
+      lisp.stack.push(Frame::DoTest { spec: spec, env: env });
+      return Ok(Step::Eval(spec.test, env));
+    }
+    Frame::DoTest { spec, env } => {
+      if lisp.is_true(value)? {
+        return Ok(Step::Exec(spec.results, env));
+      }
+      lisp.stack.push(Frame::DoBody { spec: spec, env: env });
+      return Ok(Step::Exec(spec.body, env));
+    }
+    Frame::DoBody { spec, env } => {
+      let done = lisp.unit()?;
+      lisp.stack.push(Frame::DoBind { spec: spec });
+      return evlis(lisp.list_proc, spec.steps, done, env, lisp);
+    }
+    Frame::Traced { trace, node } => {
+      if let Some(node) = lisp.traces.get_mut(&trace).and_then(|nodes| nodes.get_mut(node)) {
+        node.result = Some(value);
+      }
+      return Ok(Step::Return(value));
+    }
+    Frame::TraceEval { trace } => {
+      let nodes = lisp.traces.remove(&trace).unwrap_or_default();
+      let tree = trace_tree(&nodes, lisp)?;
+      return Ok(Step::Return(tree));
+    }
+    Frame::Module { name, env, exports } => {
+      let module = lisp.env_new(None)?;
+      for export in exports {
+        let key = match lisp.heap.get(export)? {
+          Object::Symbol(symbol) => symbol.0,
+          _ => return Err(Error::Type),
+        };
+        let value = lookup(&key, env, lisp)?;
+        define(module, export, value, lisp)?;
+      }
+      lisp.modules.insert(name, module);
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    Frame::Branch { conseq, alt, env } => {
+      if lisp.is_true(value)? {
+        return Ok(Step::Run(conseq, env));
+      }
+      return Ok(Step::Run(alt, env));
+    }
+    Frame::Seq { body, next, env } => {
+      return sequence(body, next, env, lisp);
+    }
+    Frame::Args { proc, todo, next, done, env } => {
+      let done = lisp.pair(value, done)?;
+      return arguments(proc, todo, next, done, env, lisp);
+    }
+    Frame::Consume { consumer, env } => {
+      let args = lisp.unpack_values(value)?;
+      return Ok(Step::Apply(consumer, args, env));
+    }
+    Frame::Receive { formals, body, env } => {
+      let args = lisp.unpack_values(value)?;
+      let local = lisp.env_new(Some(env))?;
+      bind(local, formals, args, lisp)?;
+      return Ok(Step::Exec(body, local));
+    }
+    Frame::Handler { .. } | Frame::Guard { .. } => {
+      return Ok(Step::Return(value));
+    }
+    Frame::Raised { depth, condition, continuable } => {
+      if continuable {
+        return Ok(Step::Return(value));
+      }
+      return raise(condition, false, depth, lisp);
+    }
+    Frame::Caught { var, clauses, env } => {
+      let local = lisp.env_new(Some(env))?;
+      define(local, var, value, lisp)?;
+      return guard_clauses(clauses, value, local, lisp);
+    }
+    Frame::Clause { clause, rest, condition, env } => {
+      if !lisp.is_true(value)? {
+        return guard_clauses(rest, condition, env, lisp);
+      }
+      let body = lisp.nth_tail(clause, 1)?;
+      if lisp.heap.get(body)?.is_unit() {
+        return Ok(Step::Return(value));
+      }
+      return Ok(Step::Exec(body, env));
+    }
+  }
+}
+
+/// Hand `condition` to the innermost handler below stack index `from`.
+/// A `with-exception-handler` handler is called where the raise
+/// happened, with the handlers from its own on out of the way; one that
+/// returns from a non-continuable raise passes the condition on to the
+/// next handler out. A `guard` unwinds to itself first, from another
+/// run if need be, the way a continuation would.
+fn raise(condition: Gc, continuable: bool, from: usize, lisp: &mut V0) -> Result<Step> {
+  let mut index = from;
+  while index > 0 {
+    index -= 1;
+    match lisp.stack[index] {
+      Frame::Handler { handler, env } => {
+        lisp.stack.push(Frame::Raised { depth: index, condition: condition, continuable: continuable });
+        let args = lisp.list(&[condition])?;
+        return Ok(Step::Apply(handler, args, env));
+      }
+      Frame::Guard { var, clauses, env } => {
+        let caught = Frame::Caught { var: var, clauses: clauses, env: env };
+        let owner = lisp.runs.iter().rev().find(|run| run.base <= index).unwrap().id;
+        let winds = lisp.winds.iter().take_while(|wind| wind.depth < index).cloned().collect();
+        let escape = Escape {
+          run: owner,
+          depth: index,
+          frames: Rc::new(vec![caught]),
+          value: condition,
+          winds: Rc::new(winds),
+        };
+        return travel(Rc::new(escape), lisp);
+      }
+      Frame::Raised { depth, .. } => {
+        index = depth;
+      }
+      _ => {}
+    }
+  }
+  let mut buf = String::new();
+  lisp.show(condition, &mut buf)?;
+  return Err(Error::Uncaught(Rc::from(buf.as_str())));
+}
+
+/// The key in `V0::modules` for a module name like `(foo bar)`.
+fn module_name(spec: Gc, lisp: &mut V0) -> Result<Rc<str>> {
+  let mut parts = vec![];
+  for part in lisp.args(spec)? {
+    match lisp.heap.get(part)? {
+      Object::Symbol(symbol) => parts.push(symbol.0),
+      _ => return Err(Error::Type),
+    }
+  }
+  guard(!parts.is_empty())?;
+  return Ok(Rc::from(parts.join(" ").as_str()));
+}
+
+/// Try each `(test expr...)` of a `guard` in turn, with `else` always
+/// passing, and raise `condition` again if none do.
+fn guard_clauses(clauses: Gc, condition: Gc, env: Gc, lisp: &mut V0) -> Result<Step> {
+  let pair = match lisp.heap.get(clauses)? {
+    Object::Unit => {
+      let from = lisp.stack.len();
+      return raise(condition, false, from, lisp);
+    }
+    Object::Pair(pair) => pair,
+    _ => return Err(Error::Type),
+  };
+  let test = match lisp.heap.get(pair.fst)? {
+    Object::Pair(clause) => clause.fst,
+    _ => return Err(Error::Type),
+  };
+  if let Object::Symbol(ref symbol) = lisp.heap.get(test)? {
+    if &*lisp.original(&symbol.0) == "else" {
+      let body = lisp.nth_tail(pair.fst, 1)?;
+      return Ok(Step::Exec(body, env));
+    }
+  }
+  lisp.stack.push(Frame::Clause { clause: pair.fst, rest: pair.snd, condition: condition, env: env });
+  return Ok(Step::Eval(test, env));
+}
+
+/// Expand `operands`, those of a form whose operator is `rules`, by
+/// the first rule that matches them.
+fn transform(rules: &SyntaxRules, operands: Gc, lisp: &mut V0) -> Result<Gc> {
+  for &(pattern, template) in rules.rules.iter() {
+    let pattern = match lisp.heap.get(pattern)? {
+      Object::Pair(pair) => pair.snd,
+      _ => return Err(Error::Type),
+    };
+    let mut binds = HashMap::new();
+    if match_pattern(pattern, operands, rules, &mut binds, lisp)? {
+      let mut renames = HashMap::new();
+      return expand(template, false, &binds, &mut renames, rules, lisp);
+    }
+  }
+  return Err(Error::Guard);
+}
+
+fn is_ellipsis(value: Gc, lisp: &V0) -> bool {
+  match lisp.heap.get(value) {
+    Ok(Object::Symbol(ref symbol)) => return &*symbol.0 == "...",
+    _ => return false,
+  }
+}
+
+/// Match `form` against `pattern`, binding its pattern variables in
+/// `binds`. A pattern followed by `...` in a list matches as many forms
+/// as the patterns after it leave over.
+fn match_pattern(
+  pattern: Gc,
+  form: Gc,
+  rules: &SyntaxRules,
+  binds: &mut HashMap<Rc<str>, Binding>,
+  lisp: &V0) -> Result<bool> {
+  match lisp.heap.get(pattern)? {
+    Object::Symbol(symbol) => {
+      if &*symbol.0 == "_" {
+        return Ok(true);
+      }
+      if rules.literals.contains(&symbol.0) {
+        match lisp.heap.get(form)? {
+          Object::Symbol(other) => return Ok(lisp.original(&other.0) == symbol.0),
+          _ => return Ok(false),
+        }
+      }
+      binds.insert(symbol.0, Binding::One(form));
+      return Ok(true);
+    }
+    Object::Pair(_) => {
+      let (items, tail) = lisp.spine(pattern)?;
+      let (forms, rest) = lisp.spine(form)?;
+      let open = !lisp.heap.get(tail)?.is_unit();
+      let dots = match items.iter().position(|&item| is_ellipsis(item, lisp)) {
+        Some(0) => return Err(Error::Guard),
+        Some(dots) => dots,
+        None => {
+          if forms.len() < items.len() || (!open && forms.len() > items.len()) {
+            return Ok(false);
+          }
+          for (&item, &form) in items.iter().zip(forms.iter()) {
+            if !match_pattern(item, form, rules, binds, lisp)? {
+              return Ok(false);
+            }
+          }
+          if open {
+            let rest = lisp.nth_tail(form, items.len())?;
+            return match_pattern(tail, rest, rules, binds, lisp);
+          }
+          return Ok(lisp.heap.get(rest)?.is_unit());
+        }
+      };
+      let repeated = items[dots - 1];
+      let before = &items[..dots - 1];
+      let after = &items[dots + 1..];
+      guard(!after.iter().any(|&item| is_ellipsis(item, lisp)))?;
+      if forms.len() < before.len() + after.len() || (!open && !lisp.heap.get(rest)?.is_unit()) {
+        return Ok(false);
+      }
+      let count = forms.len() - before.len() - after.len();
+      let (head, forms) = forms.split_at(before.len());
+      let (middle, last) = forms.split_at(count);
+      for (&item, &form) in before.iter().chain(after.iter()).zip(head.iter().chain(last.iter())) {
+        if !match_pattern(item, form, rules, binds, lisp)? {
+          return Ok(false);
+        }
+      }
+      let mut vars = vec![];
+      pattern_vars(repeated, rules, &mut vars, lisp)?;
+      let mut each: Vec<Vec<Binding>> = vec![vec![]; vars.len()];
+      for &form in middle.iter() {
+        let mut inner = HashMap::new();
+        if !match_pattern(repeated, form, rules, &mut inner, lisp)? {
+          return Ok(false);
+        }
+        for (var, seq) in vars.iter().zip(each.iter_mut()) {
+          seq.push(inner.remove(var).ok_or(Error::Guard)?);
+        }
+      }
+      for (var, seq) in vars.into_iter().zip(each) {
+        binds.insert(var, Binding::Many(seq));
+      }
+      if open {
+        return match_pattern(tail, rest, rules, binds, lisp);
+      }
+      return Ok(true);
+    }
+    _ => {
+      return is_equal(pattern, form, lisp);
+    }
+  }
+}
+
+/// The pattern variables in `pattern`.
+fn pattern_vars(pattern: Gc, rules: &SyntaxRules, vars: &mut Vec<Rc<str>>, lisp: &V0) -> Result<()> {
+  match lisp.heap.get(pattern)? {
+    Object::Symbol(symbol) => {
+      let special = &*symbol.0 == "_" || &*symbol.0 == "...";
+      if !special && !rules.literals.contains(&symbol.0) {
+        vars.push(symbol.0);
+      }
+    }
+    Object::Pair(pair) => {
+      pattern_vars(pair.fst, rules, vars, lisp)?;
+      pattern_vars(pair.snd, rules, vars, lisp)?;
+    }
+    _ => {}
+  }
+  return Ok(());
+}
+
+/// Fill in `template` from `binds`. Every other symbol is an identifier
+/// the macro introduces, and gets renamed, the same way throughout one
+/// expansion; see `Alias`. An element followed by `...` is repeated
+/// once per binding of the pattern variables in it matched under an
+/// ellipsis, and `(... template)` stands for `template` with its
+/// ellipses taken literally.
+fn expand(
+  template: Gc,
+  escaped: bool,
+  binds: &HashMap<Rc<str>, Binding>,
+  renames: &mut HashMap<Rc<str>, Gc>,
+  rules: &SyntaxRules,
+  lisp: &mut V0) -> Result<Gc> {
+  match lisp.heap.get(template)? {
+    Object::Symbol(symbol) => {
+      match binds.get(&symbol.0) {
+        Some(&Binding::One(form)) => return Ok(form),
+        Some(&Binding::Many(_)) => return Err(Error::Guard),
+        None if escaped && &*symbol.0 == "..." => return Ok(template),
+        None => return lisp.rename(&symbol.0, rules.env, renames),
+      }
+    }
+    Object::Pair(_) => {
+      let (items, tail) = lisp.spine(template)?;
+      if !escaped && items.len() == 2 && is_ellipsis(items[0], lisp) && lisp.heap.get(tail)?.is_unit() {
+        return expand(items[1], true, binds, renames, rules, lisp);
+      }
+      let mut out = vec![];
+      let mut index = 0;
+      while index < items.len() {
+        let mut depth = 0;
+        while !escaped && items.get(index + 1 + depth).is_some_and(|&next| is_ellipsis(next, lisp)) {
+          depth += 1;
+        }
+        if depth == 0 {
+          out.push(expand(items[index], escaped, binds, renames, rules, lisp)?);
+        } else {
+          expand_many(items[index], depth, binds, renames, rules, &mut out, lisp)?;
+        }
+        index += 1 + depth;
+      }
+      let mut result = expand(tail, escaped, binds, renames, rules, lisp)?;
+      for &x in out.iter().rev() {
+        result = lisp.pair(x, result)?;
+      }
+      return Ok(result);
+    }
+    _ => {
+      return Ok(template);
+    }
+  }
+}
+
+/// Expand `template` under `depth` ellipses into `out`.
+fn expand_many(
+  template: Gc,
+  depth: usize,
+  binds: &HashMap<Rc<str>, Binding>,
+  renames: &mut HashMap<Rc<str>, Gc>,
+  rules: &SyntaxRules,
+  out: &mut Vec<Gc>,
+  lisp: &mut V0) -> Result<()> {
+  let mut vars = vec![];
+  pattern_vars(template, rules, &mut vars, lisp)?;
+  let mut repeated = vec![];
+  for var in vars {
+    if let Some(Binding::Many(seq)) = binds.get(&var) {
+      repeated.push((var, seq));
+    }
+  }
+  let count = repeated.first().ok_or(Error::Guard)?.1.len();
+  guard(repeated.iter().all(|&(_, seq)| seq.len() == count))?;
+  for index in 0..count {
+    let mut inner = binds.clone();
+    for &(ref var, seq) in repeated.iter() {
+      inner.insert(var.clone(), seq[index].clone());
+    }
+    if depth == 1 {
+      out.push(expand(template, false, &inner, renames, rules, lisp)?);
+    } else {
+      expand_many(template, depth - 1, &inner, renames, rules, out, lisp)?;
+    }
+  }
+  return Ok(());
+}
+
+/// Whether a handler gets to see `error` as a condition: the errors a
+/// program can cause and recover from, as opposed to the machine
+/// running out of room or finding its heap broken.
+fn is_raisable(error: &Error) -> bool {
+  return matches!(error,
+    Error::Stub | Error::Read | Error::Type | Error::Guard | Error::Unbound(_)
+      | Error::Arity { .. } | Error::Mismatch { .. } | Error::Io(_) | Error::ReadAt { .. });
+}
+
+fn has_handler(lisp: &V0) -> bool {
+  return lisp.stack.iter().any(|frame| matches!(frame, Frame::Handler { .. } | Frame::Guard { .. }));
+}
+
+fn run(step: Step, lisp: &mut V0) -> Result<Gc> {
+  let base = lisp.stack.len();
+  let id = lisp.run_id;
+  lisp.run_id += 1;
+  lisp.runs.push(Run { id: id, base: base });
+  let mut step = step;
+  loop {
+    if lisp.incremental {
+      safe_point(&step, lisp);
+    } else if lisp.heap.max_capacity.is_some() {
+      bounded_safe_point(&step, lisp);
+    }
+    match advance(step, id, base, lisp) {
+      Ok(Advance::Next(next)) => {
+        step = next;
+      }
+      Ok(Advance::Done(value)) => {
+        lisp.runs.pop();
+        end_traces(lisp);
+        return Ok(value);
+      }
+      Err(error) => {
+        lisp.runs.pop();
+        end_traces(lisp);
+        return Err(error);
+      }
+    }
+  }
+}
+
+/// Forget the traces of any `trace-eval` control left some other way
+/// than returning, once nothing's left on the stack to go back to.
+fn end_traces(lisp: &mut V0) {
+  if lisp.stack.is_empty() {
+    lisp.traces.clear();
+  }
+}
+
+/// Note an evaluation of `value` in the innermost `trace-eval` it's
+/// happening inside, if there is one, under the evaluation it's part
+/// of, and push the frame that notes what it comes to.
+fn trace(value: Gc, lisp: &mut V0) {
+  let found = lisp.stack.iter().rev().find_map(|frame| match *frame {
+    Frame::TraceEval { trace } => Some((trace, None)),
+    Frame::Traced { trace, node } => Some((trace, Some(node))),
+    _ => None,
+  });
+  let (trace, parent) = match found {
+    Some(found) => found,
+    None => return,
+  };
+  if let Some(nodes) = lisp.traces.get_mut(&trace) {
+    nodes.push(TraceNode { expr: value, result: None, parent: parent });
+    let node = nodes.len() - 1;
+    lisp.stack.push(Frame::Traced { trace: trace, node: node });
+  }
+}
+
+/// The tree of the evaluations in `nodes`, as `trace-eval` returns it:
+/// each one a list of its expression, its value, and the trees of the
+/// evaluations it took, in order. Evaluations control left without
+/// their returning are left out, and the ones they took with them.
+fn trace_tree(nodes: &[TraceNode], lisp: &mut V0) -> Result<Gc> {
+  // An evaluation comes after the one it's part of, so going backwards
+  // every subtree is done before it's needed.
+  let mut children: Vec<Vec<Gc>> = nodes.iter().map(|_| vec![]).collect();
+  let mut tree = None;
+  for (index, node) in nodes.iter().enumerate().rev() {
+    let result = match node.result {
+      Some(result) => result,
+      None => continue,
+    };
+    let mut items = vec![node.expr, result];
+    items.extend(children[index].iter().rev());
+    let list = lisp.list(&items)?;
+    match node.parent {
+      Some(parent) => children[parent].push(list),
+      None => tree = Some(list),
+    }
+  }
+  return match tree {
+    Some(tree) => Ok(tree),
+    None => lisp.unit(),
+  };
+}
+
+enum Advance {
+  Next(Step),
+  Done(Gc),
+}
+
+/// Take one step of the run `id`, whose frames start at `base`: to
+/// the step after, or out of the run with its value. An error ends the
+/// run, and unwinds its frames before it's returned.
+fn advance(step: Step, id: usize, base: usize, lisp: &mut V0) -> Result<Advance> {
+  let next = match step {
+    Step::Eval(value, env) => {
+      if !lisp.traces.is_empty() {
+        trace(value, lisp);
+      }
+      eval(value, env, lisp)
+    }
+    Step::Exec(value, env) => exec(value, env, lisp),
+    Step::Apply(proc, value, env) => apply(proc, value, env, lisp),
+    Step::Run(code, env) => perform(&code, env, lisp),
+    Step::Return(value) => {
+      if lisp.stack.len() == base {
+        return Ok(Advance::Done(value));
+      }
+      let frame = lisp.stack.pop().unwrap();
+      resume(frame, value, lisp)
+    }
+  };
+  // An error from a primitive is raised as a string of its message,
+  // where it happened, if there's anything to catch it.
+  let next = match next {
+    Err(error) if is_raisable(&error) && has_handler(lisp) => {
+      let message = error.to_string();
+      let from = lisp.stack.len();
+      lisp.heap.put(Object::Str(Rc::from(message.as_str())))
+        .and_then(|condition| raise(condition, false, from, lisp))
+    }
+    next => next,
+  };
+  match next {
+    Ok(next) => {
+      return Ok(Advance::Next(next));
+    }
+    Err(Error::Escape) if lisp.escape.as_ref().is_some_and(|escape| escape.run == id) => {
+      let escape = lisp.escape.take().unwrap();
+      lisp.stack.truncate(escape.depth);
+      lisp.stack.extend(escape.frames.iter().cloned());
+      return Ok(Advance::Next(Step::Return(escape.value)));
+    }
+    Err(error) => {
+      lisp.stack.truncate(base);
+      while lisp.winds.last().is_some_and(|wind| wind.depth >= base) {
+        lisp.unwind();
+      }
+      return Err(error);
+    }
+  }
+}
+
+/// Between two steps of the outermost run nothing outside the heap
+/// holds a pointer except the roots and `step`, so this is where an
+/// incremental collection can begin, and where one that allocation
+/// finished gets tidied up after.
+fn safe_point(step: &Step, lisp: &mut V0) {
+  if lisp.runs.len() != 1 || !lisp.heap.is_idle() {
+    return;
+  }
+  if lisp.collecting {
+    lisp.collecting = false;
+    lisp.prune();
+  }
+  if lisp.heap.is_filling() {
+    let mut roots = vec![];
+    step.pointers(&mut roots);
+    lisp.roots(&mut roots);
+    lisp.heap.begin(&roots);
+    lisp.collecting = true;
+  }
+}
+
+/// A bounded heap can't grow its way out of filling up, so once it's
+/// three quarters full the outermost run collects it between steps,
+/// where nothing's live that the step and the roots don't reach. A heap
+/// that's mostly live data would collect at every step, so the next
+/// collection waits for half the room this one freed to be used up.
+fn bounded_safe_point(step: &Step, lisp: &mut V0) {
+  if lisp.runs.len() != 1 || !lisp.heap.is_filling() || lisp.heap.time < lisp.next_collection {
+    return;
+  }
+  let mut roots = vec![];
+  step.pointers(&mut roots);
+  lisp.gc_with(&roots);
+  lisp.next_collection = lisp.heap.time.saturating_add(lisp.heap.available() / 2);
+}
+
+fn native(
+  nat: &Nat,
+  value: Gc,
+  env: Gc,
+  lisp: &mut V0) -> Result<Step> {
+  match nat {
+    &Nat::Vau => {
+      let args = lisp.args(value)?;
+      guard(args.len() >= 2)?;
+      let body = lisp.nth_tail(value, 2)?;
+      let names = slot_names(args[0], args[1], lisp)?;
+      let abs = Abs { head: args[0], tail: body, lexical: env, dynamic: args[1], names: names };
+      let proc = lisp.heap.put(Object::Proc(Proc::Abs(abs)))?;
+      return Ok(Step::Return(proc));
+    }
+    &Nat::Lambda => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty())?;
+      let body = lisp.nth_tail(value, 1)?;
+      let dynamic = lisp.unit()?;
+      let names = slot_names(args[0], dynamic, lisp)?;
+      let abs = Abs { head: args[0], tail: body, lexical: env, dynamic: dynamic, names: names };
+      let proc = lisp.heap.put(Object::Proc(Proc::Abs(abs)))?;
+      let app = lisp.heap.put(Object::Proc(Proc::App(App(proc))))?;
+      return Ok(Step::Return(app));
+    }
+    &Nat::Define => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      lisp.stack.push(Frame::Define { head: args[0], env: env });
+      return Ok(Step::Eval(args[1], env));
+    }
+    &Nat::If => {
+      let args = lisp.args(value)?;
+      let alt;
+      match args.len() {
+        2 => { alt = lisp.unit()? }
+        3 => { alt = args[2] }
+        got => { return Err(Error::Arity { expected: 3, got: got }) }
+      }
+      lisp.stack.push(Frame::If { conseq: args[1], alt: alt, env: env });
+      return Ok(Step::Eval(args[0], env));
+    }
+    &Nat::Sequence => {
+      return Ok(Step::Exec(value, env));
+    }
+    &Nat::Quote => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      return Ok(Step::Return(args[0]));
+    }
+    &Nat::Pair | &Nat::Cons => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let pair = lisp.pair(args[0], args[1])?;
+      return Ok(Step::Return(pair));
+    }
+    &Nat::Fst | &Nat::Snd | &Nat::Car | &Nat::Cdr => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      match lisp.heap.get(args[0])? {
+        Object::Pair(ref pair) => {
+          if let &Nat::Fst | &Nat::Car = nat {
+            return Ok(Step::Return(pair.fst));
+          }
+          return Ok(Step::Return(pair.snd));
+        }
+        _ => {
+          return Err(Error::Type);
+        }
+      }
+    }
+    &Nat::SetFst => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let pair = match lisp.heap.get(args[0])? {
+        Object::Pair(pair) => pair,
+        _ => return Err(Error::Type),
+      };
+      let target = lisp.thaw(args[0])?;
+      lisp.heap.set(target, Object::Pair(Pair { fst: args[1], ..pair }))?;
+      return Ok(Step::Return(target));
+    }
+    &Nat::Freeze => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      lisp.freeze(args[0])?;
+      return Ok(Step::Return(args[0]));
+    }
+    &Nat::StorePut => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let id = lisp.store_put(args[0])?;
+      let result = lisp.number(id as i64)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::StoreRef => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let id = lisp.index(args[0])?;
+      let result = lisp.store_ref(id).ok_or(Error::Guard)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::SandboxEval => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let src = lisp.string(args[0])?;
+      let steps = lisp.index(args[1])?;
+      let result = lisp.sandbox_eval(&src, steps)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Canonicalize => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let result = lisp.canonicalize(args[0])?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::ValueSize => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let size = lisp.value_size(args[0])?;
+      let result = lisp.number(size as i64)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::And | &Nat::Or => {
+      let is_and = matches!(nat, Nat::And);
+      let mut result = is_and;
+      for arg in lisp.args(value)? {
+        match lisp.heap.get(arg)? {
+          Object::Bool(flag) => {
+            if is_and {
+              result = result && flag;
+            } else {
+              result = result || flag;
+            }
+          }
+          _ => {
+            return Err(Error::Type);
+          }
+        }
+      }
+      let result = lisp.heap.put(Object::Bool(result))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Not => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      match lisp.heap.get(args[0])? {
+        Object::Bool(flag) => {
+          let result = lisp.heap.put(Object::Bool(!flag))?;
+          return Ok(Step::Return(result));
+        }
+        _ => {
+          return Err(Error::Type);
+        }
+      }
+    }
+    &Nat::Wrap => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      match lisp.heap.get(args[0])? {
+        Object::Proc(_) => {
+          let app = lisp.heap.put(Object::Proc(Proc::App(App(args[0]))))?;
+          return Ok(Step::Return(app));
+        }
+        _ => {
+          return Err(Error::Type);
+        }
+      }
+    }
+    &Nat::Unwrap => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      match lisp.heap.get(args[0])? {
+        Object::Proc(Proc::App(ref app)) => {
+          return Ok(Step::Return(app.0));
+        }
+        _ => {
+          return Err(Error::Type);
+        }
+      }
+    }
+    &Nat::TreeMap => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let tree = tree_map(args[0], args[1], env, lisp)?;
+      return Ok(Step::Return(tree));
+    }
+    &Nat::InternStats => {
+      let args = lisp.args(value)?;
+      arity(&args, 0)?;
+      let counts = [lisp.intern_hits, lisp.intern_misses, lisp.small_hits, lisp.small_misses];
+      let mut stats = vec![];
+      for count in counts {
+        stats.push(lisp.number(count as i64)?);
+      }
+      let stats = lisp.list(&stats)?;
+      return Ok(Step::Return(stats));
+    }
+    &Nat::Values => {
+      let args = lisp.args(value)?;
+      if args.len() == 1 {
+        return Ok(Step::Return(args[0]));
+      }
+      let result = lisp.heap.put(Object::MultipleValues(Rc::new(args)))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::CallWithValues => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let producer = combiner(args[0], lisp)?;
+      let consumer = combiner(args[1], lisp)?;
+      lisp.stack.push(Frame::Consume { consumer: consumer, env: env });
+      let unit = lisp.unit()?;
+      return Ok(Step::Apply(producer, unit, env));
+    }
+    &Nat::Receive => {
+      let args = lisp.args(value)?;
+      guard(args.len() >= 2)?;
+      let body = lisp.nth_tail(value, 2)?;
+      lisp.stack.push(Frame::Receive { formals: args[0], body: body, env: env });
+      return Ok(Step::Eval(args[1], env));
+    }
+    &Nat::Raise | &Nat::RaiseContinuable => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let continuable = matches!(nat, Nat::RaiseContinuable);
+      let from = lisp.stack.len();
+      return raise(args[0], continuable, from, lisp);
+    }
+    &Nat::WithExceptionHandler => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let handler = combiner(args[0], lisp)?;
+      let thunk = combiner(args[1], lisp)?;
+      lisp.stack.push(Frame::Handler { handler: handler, env: env });
+      let unit = lisp.unit()?;
+      return Ok(Step::Apply(thunk, unit, env));
+    }
+    &Nat::Guard => {
+      let args = lisp.args(value)?;
+      guard(args.len() >= 2)?;
+      let (var, clauses) = match lisp.heap.get(args[0])? {
+        Object::Pair(pair) => (pair.fst, pair.snd),
+        _ => return Err(Error::Type),
+      };
+      let body = lisp.nth_tail(value, 1)?;
+      lisp.stack.push(Frame::Guard { var: var, clauses: clauses, env: env });
+      return Ok(Step::Exec(body, env));
+    }
+    &Nat::CollectGarbage => {
+      let args = lisp.args(value)?;
+      arity(&args, 0)?;
+      // A native further out could be holding values the collector
+      // can't see, anywhere but the outermost run. Values the host
+      // holds without `root` are its own lookout, as with `gc`.
+      guard(lisp.runs.len() == 1)?;
+      lisp.gc_with(&[value, env]);
+      let stats = lisp.stats();
+      let mut report = vec![];
+      for count in [stats.live, stats.free, stats.capacity, stats.free_runs] {
+        report.push(lisp.number(count as i64)?);
+      }
+      let result = lisp.list(&report)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Reserve => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let capacity = lisp.index(args[0])?;
+      lisp.reserve(capacity)?;
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    &Nat::Gensym => {
+      let args = lisp.args(value)?;
+      arity(&args, 0)?;
+      let symbol = lisp.gensym()?;
+      return Ok(Step::Return(symbol));
+    }
+    &Nat::Add | &Nat::Sub | &Nat::Mul | &Nat::Div | &Nat::Rem | &Nat::Neg | &Nat::Abs_ => {
+      let args = lisp.args(value)?;
+      let result = match arith(nat, &args, lisp)? {
+        Some((numer, denom)) => lisp.rational(numer, denom)?,
+        None => big_arith(nat, &args, lisp)?,
+      };
+      return Ok(Step::Return(result));
+    }
+    &Nat::Numerator | &Nat::Denominator => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let (numer, denom) = lisp.parts(args[0])?;
+      let result = match nat {
+        &Nat::Numerator => numer,
+        _ => denom,
+      };
+      return Ok(Step::Return(result));
+    }
+    &Nat::ExactToInexact | &Nat::InexactToExact => {
+      // Every number is exact so far, so both are the identity.
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      if !lisp.is_number(args[0])? {
+        return Err(Error::Type);
+      }
+      return Ok(Step::Return(args[0]));
+    }
+    &Nat::NumEq | &Nat::NumLt | &Nat::NumGt | &Nat::NumLe | &Nat::NumGe => {
+      let args = lisp.args(value)?;
+      let flag = match compare(nat, &args, lisp)? {
+        Some(flag) => flag,
+        None => big_compare(nat, &args, lisp)?,
+      };
+      let result = lisp.heap.put(Object::Bool(flag))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::ValidateHeap => {
+      let args = lisp.args(value)?;
+      arity(&args, 0)?;
+      let mut roots = vec![value, env];
+      lisp.roots(&mut roots);
+      lisp.heap.check_invariants(&roots)?;
+      let t = lisp.t()?;
+      return Ok(Step::Return(t));
+    }
+    &Nat::ListProc => {
+      return Ok(Step::Return(value));
+    }
+    &Nat::Length => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let count = lisp.args(args[0])?.len();
+      let count = lisp.number(count as i64)?;
+      return Ok(Step::Return(count));
+    }
+    &Nat::Append => {
+      let args = lisp.args(value)?;
+      let (last, init) = match args.split_last() {
+        Some(split) => split,
+        None => return Ok(Step::Return(value)),
+      };
+      let mut xs = vec![];
+      for arg in init.iter() {
+        xs.extend(lisp.args(*arg)?);
+      }
+      let mut result = *last;
+      for x in xs.iter().rev() {
+        result = lisp.pair(*x, result)?;
+      }
+      return Ok(Step::Return(result));
+    }
+    &Nat::Reverse => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let mut result = lisp.unit()?;
+      for x in lisp.args(args[0])? {
+        result = lisp.pair(x, result)?;
+      }
+      return Ok(Step::Return(result));
+    }
+    &Nat::Map => {
+      let args = lisp.args(value)?;
+      guard(args.len() >= 2)?;
+      let mut lists = vec![];
+      for arg in args[1..].iter() {
+        lists.push(lisp.args(*arg)?);
+      }
+      let count = lists.iter().map(|xs| xs.len()).min().unwrap();
+      let mut results = Vec::with_capacity(count);
+      for index in 0..count {
+        let mut xs = lisp.unit()?;
+        for list in lists.iter().rev() {
+          xs = lisp.pair(list[index], xs)?;
+        }
+        results.push(lisp.call(args[0], xs, env)?);
+      }
+      let result = lisp.list(&results)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Filter => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let mut results = vec![];
+      for x in lisp.args(args[1])? {
+        let xs = lisp.list(&[x])?;
+        let flag = lisp.call(args[0], xs, env)?;
+        if lisp.is_true(flag)? {
+          results.push(x);
+        }
+      }
+      let result = lisp.list(&results)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Foldl | &Nat::Foldr => {
+      // Both folds call (f element accumulator), as in SRFI 1.
+      let args = lisp.args(value)?;
+      arity(&args, 3)?;
+      let mut xs = lisp.args(args[2])?;
+      if let &Nat::Foldr = nat {
+        xs.reverse();
+      }
+      let mut acc = args[1];
+      for x in xs {
+        let ys = lisp.list(&[x, acc])?;
+        acc = lisp.call(args[0], ys, env)?;
+      }
+      return Ok(Step::Return(acc));
+    }
+    &Nat::CallCc => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let proc = combiner(args[0], lisp)?;
+      let run = lisp.runs.last().unwrap();
+      let frames = lisp.stack[run.base..].to_vec();
+      let winds = Rc::new(lisp.winds.clone());
+      let cont = Cont { run: run.id, depth: run.base, frames: Rc::new(frames), extent: None, winds: winds };
+      let cont = lisp.heap.put(Object::Proc(Proc::Cont(cont)))?;
+      let cont = lisp.heap.put(Object::Proc(Proc::App(App(cont))))?;
+      let unit = lisp.unit()?;
+      let args = lisp.pair(cont, unit)?;
+      return Ok(Step::Apply(proc, args, env));
+    }
+    &Nat::DynamicWind => {
+      let args = lisp.args(value)?;
+      arity(&args, 3)?;
+      let before = combiner(args[0], lisp)?;
+      let during = combiner(args[1], lisp)?;
+      let after = combiner(args[2], lisp)?;
+      lisp.stack.push(Frame::Winding { before: before, during: during, after: after, env: env });
+      let unit = lisp.unit()?;
+      return Ok(Step::Apply(before, unit, env));
+    }
+    &Nat::MakeParameter => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let param = lisp.heap.put(Object::Proc(Proc::Param(Param { value: args[0] })))?;
+      let param = lisp.heap.put(Object::Proc(Proc::App(App(param))))?;
+      return Ok(Step::Return(param));
+    }
+    &Nat::Parameterize => {
+      let args = lisp.args(value)?;
+      guard(args.len() >= 2)?;
+      let body = lisp.nth_tail(value, 1)?;
+      let mut exprs = vec![];
+      for binding in lisp.args(args[0])? {
+        let binding = lisp.args(binding)?;
+        arity(&binding, 2)?;
+        exprs.extend(binding);
+      }
+      let todo = lisp.list(&exprs)?;
+      let done = lisp.unit()?;
+      lisp.stack.push(Frame::Parameterize { body: body, env: env });
+      return evlis(lisp.list_proc, todo, done, env, lisp);
+    }
+    &Nat::SyntaxRules => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty())?;
+      let mut literals = vec![];
+      for literal in lisp.args(args[0])? {
+        match lisp.heap.get(literal)? {
+          Object::Symbol(symbol) => literals.push(symbol.0),
+          _ => return Err(Error::Type),
+        }
+      }
+      let mut rules = vec![];
+      for &rule in args[1..].iter() {
+        let rule = lisp.args(rule)?;
+        arity(&rule, 2)?;
+        rules.push((rule[0], rule[1]));
+      }
+      let rules = SyntaxRules { literals: Rc::new(literals), rules: Rc::new(rules), env: env };
+      let rules = lisp.heap.put(Object::Macro(rules))?;
+      return Ok(Step::Return(rules));
+    }
+    &Nat::Do => {
+      let args = lisp.args(value)?;
+      guard(args.len() >= 2)?;
+      let (mut vars, mut inits, mut steps) = (vec![], vec![], vec![]);
+      for spec in lisp.args(args[0])? {
+        let parts = lisp.args(spec)?;
+        guard(parts.len() == 2 || parts.len() == 3)?;
+        match lisp.heap.get(parts[0])? {
+          Object::Symbol(_) => {}
+          _ => return Err(Error::Type),
+        }
+        vars.push(parts[0]);
+        inits.push(parts[1]);
+        steps.push(*parts.get(2).unwrap_or(&parts[0]));
+      }
+      let clause = lisp.args(args[1])?;
+      guard(!clause.is_empty())?;
+      let spec = Loop {
+        vars: lisp.list(&vars)?,
+        steps: lisp.list(&steps)?,
+        test: clause[0],
+        results: lisp.nth_tail(args[1], 1)?,
+        body: lisp.nth_tail(value, 2)?,
+        env: env,
+      };
+      let todo = lisp.list(&inits)?;
+      let done = lisp.unit()?;
+      lisp.stack.push(Frame::DoBind { spec: spec });
+      return evlis(lisp.list_proc, todo, done, env, lisp);
+    }
+    &Nat::DefineModule => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty())?;
+      let name = module_name(args[0], lisp)?;
+      let body = lisp.nth_tail(value, 1)?;
+      let global = lisp.env;
+      let module = lisp.env_new(Some(global))?;
+      lisp.stack.push(Frame::Module { name: name, env: module, exports: vec![] });
+      return Ok(Step::Exec(body, module));
+    }
+    &Nat::Export => {
+      let names = lisp.args(value)?;
+      for &name in names.iter() {
+        match lisp.heap.get(name)? {
+          Object::Symbol(_) => {}
+          _ => return Err(Error::Type),
+        }
+      }
+      let exports = lisp.stack.iter_mut().rev().find_map(|frame| match frame {
+        &mut Frame::Module { ref mut exports, .. } => Some(exports),
+        _ => None,
+      });
+      exports.ok_or(Error::Guard)?.extend(names);
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    &Nat::Import => {
+      for spec in lisp.args(value)? {
+        let name = module_name(spec, lisp)?;
+        let module = *lisp.modules.get(&name).ok_or(Error::Unbound(name))?;
+        for (name, value) in lisp.bindings(module, false)? {
+          define(env, name, value, lisp)?;
+        }
+      }
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    &Nat::DefineSyntax => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      lisp.stack.push(Frame::Define { head: args[0], env: env });
+      return Ok(Step::Eval(args[1], env));
+    }
+    &Nat::CallEc => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let proc = combiner(args[0], lisp)?;
+      let id = lisp.extent_id;
+      lisp.extent_id += 1;
+      let cont = Cont {
+        run: lisp.runs.last().unwrap().id,
+        depth: lisp.stack.len(),
+        frames: Rc::new(vec![]),
+        extent: Some(id),
+        winds: Rc::new(lisp.winds.clone()),
+      };
+      lisp.stack.push(Frame::Extent { id: id });
+      let cont = lisp.heap.put(Object::Proc(Proc::Cont(cont)))?;
+      let cont = lisp.heap.put(Object::Proc(Proc::App(App(cont))))?;
+      let args = lisp.list(&[cont])?;
+      return Ok(Step::Apply(proc, args, env));
+    }
+    &Nat::Eq | &Nat::Eqv | &Nat::Equal => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let flag = match *nat {
+        Nat::Eq => is_eq(args[0], args[1], lisp)?,
+        Nat::Eqv => is_eqv(args[0], args[1], lisp)?,
+        _ => is_equal(args[0], args[1], lisp)?,
+      };
+      let result = lisp.heap.put(Object::Bool(flag))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::ProperTail => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      match lisp.heap.get(args[0])? {
+        Object::Pair(ref pair) => {
+          let result = lisp.heap.put(Object::Bool(pair.is_list))?;
+          return Ok(Step::Return(result));
+        }
+        _ => {
+          return Err(Error::Type);
+        }
+      }
+    }
+    &Nat::IsNull | &Nat::IsPair | &Nat::IsSymbol | &Nat::IsBool
+      | &Nat::IsNumber | &Nat::IsProc | &Nat::IsEnv | &Nat::IsVector | &Nat::IsString | &Nat::IsChar
+      | &Nat::IsBytevector | &Nat::IsHashTable | &Nat::IsEof => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let flag = match (nat, lisp.heap.get(args[0])?) {
+        (&Nat::IsNull, Object::Unit) => true,
+        (&Nat::IsPair, Object::Pair(_)) => true,
+        (&Nat::IsSymbol, Object::Symbol(_)) => true,
+        (&Nat::IsBool, Object::Bool(_)) => true,
+        (&Nat::IsNumber, Object::Number(_)) => true,
+        (&Nat::IsNumber, Object::Rational { .. }) => true,
+        #[cfg(feature = "bignum")]
+        (&Nat::IsNumber, Object::BigInt(_)) | (&Nat::IsNumber, Object::BigRational { .. }) => true,
+        (&Nat::IsProc, Object::Proc(_)) => true,
+        (&Nat::IsEnv, Object::Env(_)) => true,
+        (&Nat::IsVector, Object::Vector(_)) => true,
+        (&Nat::IsString, Object::Str(_)) => true,
+        (&Nat::IsChar, Object::Char(_)) => true,
+        (&Nat::IsBytevector, Object::Bytevector(_)) => true,
+        (&Nat::IsHashTable, Object::HashMap(_)) => true,
+        (&Nat::IsEof, Object::Eof) => true,
+        _ => false,
+      };
+      let result = lisp.heap.put(Object::Bool(flag))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Apply => {
+      let args = lisp.args(value)?;
+      guard(args.len() >= 2)?;
+      let proc = combiner(args[0], lisp)?;
+      let mut xs = args[args.len() - 1];
+      lisp.args(xs)?;
+      for arg in args[1..args.len() - 1].iter().rev() {
+        xs = lisp.pair(*arg, xs)?;
+      }
+      return Ok(Step::Apply(proc, xs, env));
+    }
+    &Nat::MakeOperative | &Nat::MakeApplicative => {
+      // (make-operative head dynamic body env) and
+      // (make-applicative head body env), where body is a list of forms.
+      let args = lisp.args(value)?;
+      let abs = match nat {
+        &Nat::MakeOperative => {
+          arity(&args, 4)?;
+          let names = slot_names(args[0], args[1], lisp)?;
+          Abs { head: args[0], tail: args[2], lexical: args[3], dynamic: args[1], names: names }
+        }
+        _ => {
+          arity(&args, 3)?;
+          let dynamic = lisp.unit()?;
+          let names = slot_names(args[0], dynamic, lisp)?;
+          Abs { head: args[0], tail: args[1], lexical: args[2], dynamic: dynamic, names: names }
+        }
+      };
+      guard(lisp.args(abs.tail).is_ok())?;
+      match lisp.heap.get(abs.lexical)? {
+        Object::Env(_) => {}
+        _ => return Err(Error::Type),
+      }
+      let mut proc = lisp.heap.put(Object::Proc(Proc::Abs(abs)))?;
+      if let &Nat::MakeApplicative = nat {
+        proc = lisp.heap.put(Object::Proc(Proc::App(App(proc))))?;
+      }
+      return Ok(Step::Return(proc));
+    }
+    &Nat::MakeVector => {
+      let args = lisp.args(value)?;
+      let fill = match args.len() {
+        1 => lisp.unit()?,
+        2 => args[1],
+        got => return Err(Error::Arity { expected: 2, got: got }),
+      };
+      let count = lisp.index(args[0])?;
+      lisp.fits(count)?;
+      let mut xs = vec![];
+      xs.try_reserve_exact(count).map_err(|_| Error::Space)?;
+      xs.resize(count, fill);
+      let vector = Rc::new(RefCell::new(xs));
+      let result = lisp.heap.put(Object::Vector(vector))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::VectorRef => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let vector = lisp.vector(args[0])?;
+      let index = lisp.index(args[1])?;
+      let result = *vector.borrow().get(index).ok_or(Error::Guard)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::VectorSet => {
+      let args = lisp.args(value)?;
+      arity(&args, 3)?;
+      let index = lisp.index(args[1])?;
+      guard(index < lisp.vector(args[0])?.borrow().len())?;
+      let target = lisp.thaw(args[0])?;
+      let vector = lisp.vector(target)?;
+      let old = vector.borrow()[index];
+      lisp.heap.shade(old);
+      vector.borrow_mut()[index] = args[2];
+      return Ok(Step::Return(target));
+    }
+    &Nat::VectorLength => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let count = lisp.vector(args[0])?.borrow().len();
+      let result = lisp.number(count as i64)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::VectorFill => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      lisp.vector(args[0])?;
+      let target = lisp.thaw(args[0])?;
+      let vector = lisp.vector(target)?;
+      for &x in vector.borrow().iter() {
+        lisp.heap.shade(x);
+      }
+      for x in vector.borrow_mut().iter_mut() {
+        *x = args[1];
+      }
+      return Ok(Step::Return(target));
+    }
+    &Nat::ListToVector => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let xs = lisp.args(args[0])?;
+      let result = lisp.heap.put(Object::Vector(Rc::new(RefCell::new(xs))))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::VectorToList => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let xs = lisp.vector(args[0])?.borrow().clone();
+      let result = lisp.list(&xs)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::MakeBytevector => {
+      let args = lisp.args(value)?;
+      let fill = match args.len() {
+        1 => 0,
+        2 => lisp.byte(args[1])?,
+        got => return Err(Error::Arity { expected: 2, got: got }),
+      };
+      let count = lisp.index(args[0])?;
+      lisp.fits(count)?;
+      let mut bytes = vec![];
+      bytes.try_reserve_exact(count).map_err(|_| Error::Space)?;
+      bytes.resize(count, fill);
+      let bytes = Rc::new(RefCell::new(bytes));
+      let result = lisp.heap.put(Object::Bytevector(bytes))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Bytevector => {
+      let mut bytes = vec![];
+      for arg in lisp.args(value)? {
+        bytes.push(lisp.byte(arg)?);
+      }
+      let result = lisp.heap.put(Object::Bytevector(Rc::new(RefCell::new(bytes))))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::BytevectorLength => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let count = lisp.bytevector(args[0])?.borrow().len();
+      let result = lisp.number(count as i64)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::BytevectorU8Ref => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let bytes = lisp.bytevector(args[0])?;
+      let index = lisp.index(args[1])?;
+      let byte = *bytes.borrow().get(index).ok_or(Error::Guard)?;
+      let result = lisp.number(byte as i64)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::BytevectorU8Set => {
+      let args = lisp.args(value)?;
+      arity(&args, 3)?;
+      let index = lisp.index(args[1])?;
+      let byte = lisp.byte(args[2])?;
+      guard(index < lisp.bytevector(args[0])?.borrow().len())?;
+      let target = lisp.thaw(args[0])?;
+      lisp.bytevector(target)?.borrow_mut()[index] = byte;
+      return Ok(Step::Return(target));
+    }
+    &Nat::BytevectorCopy => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty())?;
+      let bytes = lisp.bytevector(args[0])?;
+      let (start, end) = lisp.range(&args, 1, bytes.borrow().len())?;
+      let copy = bytes.borrow()[start..end].to_vec();
+      let result = lisp.heap.put(Object::Bytevector(Rc::new(RefCell::new(copy))))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Utf8Decode => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty())?;
+      let bytes = lisp.bytevector(args[0])?;
+      let (start, end) = lisp.range(&args, 1, bytes.borrow().len())?;
+      let text = std::str::from_utf8(&bytes.borrow()[start..end])
+        .map(Rc::from)
+        .map_err(|_| Error::Read)?;
+      let result = lisp.heap.put(Object::Str(text))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Utf8Encode => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty())?;
+      let text = match lisp.heap.get(args[0])? {
+        Object::Str(text) => text,
+        _ => return Err(Error::Type),
+      };
+      let (start, end) = lisp.range(&args, 1, text.chars().count())?;
+      let text: String = text.chars().skip(start).take(end - start).collect();
+      let bytes = Rc::new(RefCell::new(text.into_bytes()));
+      let result = lisp.heap.put(Object::Bytevector(bytes))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Base64Encode => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let text = base64_encode(&lisp.bytevector(args[0])?.borrow());
+      lisp.fits(text.len())?;
+      let result = lisp.heap.put(Object::Str(Rc::from(text.as_str())))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Base64Decode => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let text = match lisp.heap.get(args[0])? {
+        Object::Str(text) => text,
+        _ => return Err(Error::Type),
+      };
+      let bytes = base64_decode(&text).ok_or(Error::Read)?;
+      let result = lisp.heap.put(Object::Bytevector(Rc::new(RefCell::new(bytes))))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::HashBytes => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let hash = fnv1a(&lisp.bytevector(args[0])?.borrow());
+      let result = lisp.number(hash as i64)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::StringLength => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let count = lisp.string(args[0])?.chars().count();
+      let result = lisp.number(count as i64)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::StringRef => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let text = lisp.string(args[0])?;
+      let index = lisp.index(args[1])?;
+      let rune = text.chars().nth(index).ok_or(Error::Guard)?;
+      let result = lisp.heap.put(Object::Char(rune))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::StringAppend => {
+      let mut buf = String::new();
+      for arg in lisp.args(value)? {
+        buf.push_str(&lisp.string(arg)?);
+        lisp.fits(buf.len())?;
+      }
+      let result = lisp.heap.put(Object::Str(Rc::from(buf.as_str())))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Substring => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty())?;
+      let text = lisp.string(args[0])?;
+      let (start, end) = lisp.range(&args, 1, text.chars().count())?;
+      let text: String = text.chars().skip(start).take(end - start).collect();
+      let result = lisp.heap.put(Object::Str(Rc::from(text.as_str())))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::StringToSymbol => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let text = lisp.string(args[0])?;
+      let result = lisp.symbol(text)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::SymbolToString => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let name = match lisp.heap.get(args[0])? {
+        Object::Symbol(symbol) => symbol.0,
+        _ => return Err(Error::Type),
+      };
+      let result = lisp.heap.put(Object::Str(name))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::NumberToString => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      if !lisp.is_number(args[0])? {
+        return Err(Error::Type);
+      }
+      let mut buf = String::new();
+      lisp.show(args[0], &mut buf)?;
+      let result = lisp.heap.put(Object::Str(Rc::from(buf.as_str())))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::StringToNumber => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let text = lisp.string(args[0])?;
+      let result = match number_literal(&text, lisp) {
+        Ok(Some(number)) => number,
+        _ => lisp.f()?,
+      };
+      return Ok(Step::Return(result));
+    }
+    &Nat::StringUpcase | &Nat::StringDowncase => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let text = lisp.string(args[0])?;
+      let text = match nat {
+        &Nat::StringUpcase => text.to_uppercase(),
+        _ => text.to_lowercase(),
+      };
+      lisp.fits(text.len())?;
+      let result = lisp.heap.put(Object::Str(Rc::from(text.as_str())))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::StringEq | &Nat::StringLt => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty())?;
+      let mut texts = vec![];
+      for &arg in args.iter() {
+        texts.push(lisp.string(arg)?);
+      }
+      let flag = texts.windows(2).all(|pair| match nat {
+        &Nat::StringEq => pair[0] == pair[1],
+        _ => pair[0] < pair[1],
+      });
+      let result = lisp.heap.put(Object::Bool(flag))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::SbNew => {
+      let mut buf = String::new();
+      for arg in lisp.args(value)? {
+        buf.push_str(&lisp.string(arg)?);
+        lisp.fits(buf.len())?;
+      }
+      let result = lisp.heap.put(Object::Builder(Rc::new(RefCell::new(buf))))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::SbAppend => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty())?;
+      let builder = lisp.builder(args[0])?;
+      for &arg in args[1..].iter() {
+        let text = lisp.string(arg)?;
+        lisp.fits(builder.borrow().len() + text.len())?;
+        builder.borrow_mut().push_str(&text);
+      }
+      return Ok(Step::Return(args[0]));
+    }
+    &Nat::SbBuild => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let text = Rc::from(lisp.builder(args[0])?.borrow().as_str());
+      let result = lisp.heap.put(Object::Str(text))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::MakeHashTable => {
+      let args = lisp.args(value)?;
+      arity(&args, 0)?;
+      let result = lisp.heap.put(Object::HashMap(Rc::new(RefCell::new(HashMap::new()))))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::HashRef => {
+      let args = lisp.args(value)?;
+      guard(args.len() == 2 || args.len() == 3)?;
+      let table = lisp.hash_table(args[0])?;
+      let key = hash_key(args[1], lisp)?;
+      let found = table.borrow().get(&key).cloned();
+      let result = found.or_else(|| args.get(2).cloned()).ok_or(Error::Guard)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::HashSet => {
+      let args = lisp.args(value)?;
+      arity(&args, 3)?;
+      let table = lisp.hash_table(args[0])?;
+      let key = hash_key(args[1], lisp)?;
+      if let Some(old) = table.borrow_mut().insert(key, args[2]) {
+        lisp.heap.shade(old);
+      }
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    &Nat::HashDelete => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let table = lisp.hash_table(args[0])?;
+      let key = hash_key(args[1], lisp)?;
+      if let Some(old) = table.borrow_mut().remove(&key) {
+        lisp.heap.shade(old);
+      }
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    &Nat::HashContains => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let table = lisp.hash_table(args[0])?;
+      let key = hash_key(args[1], lisp)?;
+      let flag = table.borrow().contains_key(&key);
+      let result = lisp.heap.put(Object::Bool(flag))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::HashKeys | &Nat::HashValues => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let mut xs = vec![];
+      for (key, value) in lisp.hash_entries(args[0])? {
+        match nat {
+          &Nat::HashKeys => xs.push(key),
+          _ => xs.push(value),
+        }
+      }
+      let result = lisp.list(&xs)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::HashSize => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let count = lisp.hash_table(args[0])?.borrow().len();
+      let result = lisp.number(count as i64)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::ProcedureSource => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let result = match procedure_source(args[0], lisp)? {
+        Some(source) => source,
+        None => lisp.f()?,
+      };
+      return Ok(Step::Return(result));
+    }
+    &Nat::ToJson => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let mut buf = String::new();
+      lisp.to_json(args[0], &mut buf)?;
+      let result = lisp.heap.put(Object::Str(Rc::from(buf.as_str())))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::ReadFile => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let path = match lisp.heap.get(args[0])? {
+        Object::Str(path) => path,
+        _ => return Err(Error::Type),
+      };
+      let src = std::fs::read_to_string(&*path).map_err(io_error)?;
+      let forms = lisp.read(&src)?;
+      let result = lisp.list(&forms)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::OpenInputFile | &Nat::OpenOutputFile => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let path = match lisp.heap.get(args[0])? {
+        Object::Str(path) => path,
+        _ => return Err(Error::Type),
+      };
+      let port = match nat {
+        &Nat::OpenInputFile => {
+          let file = std::fs::File::open(&*path).map_err(io_error)?;
+          Port::new(&path, Some(Box::new(std::io::BufReader::new(file))), None)
+        }
+        _ => {
+          let file = std::fs::File::create(&*path).map_err(io_error)?;
+          Port::new(&path, None, Some(Box::new(std::io::BufWriter::new(file))))
+        }
+      };
+      let result = lisp.heap.put(Object::Port(Rc::new(RefCell::new(port))))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::ClosePort => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      lisp.port(args[0])?.borrow_mut().close()?;
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    &Nat::ReadChar | &Nat::PeekChar => {
+      let args = lisp.args(value)?;
+      guard(args.len() <= 1)?;
+      let port = lisp.port_or(args.first(), "current-input-port", env)?;
+      let rune = match nat {
+        &Nat::ReadChar => port.borrow_mut().read()?,
+        _ => port.borrow_mut().peek()?,
+      };
+      let result = match rune {
+        Some(rune) => lisp.heap.put(Object::Char(rune))?,
+        None => lisp.heap.put(Object::Eof)?,
+      };
+      return Ok(Step::Return(result));
+    }
+    &Nat::WriteChar | &Nat::WriteString => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty() && args.len() <= 2)?;
+      let text = match (nat, lisp.heap.get(args[0])?) {
+        (&Nat::WriteChar, Object::Char(rune)) => Rc::from(rune.to_string().as_str()),
+        (&Nat::WriteString, Object::Str(text)) => text,
+        _ => return Err(Error::Type),
+      };
+      let port = lisp.port_or(args.get(1), "current-output-port", env)?;
+      port.borrow_mut().write(&text)?;
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    &Nat::IsBound | &Nat::Unbind => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty() && args.len() <= 2)?;
+      let name = match lisp.heap.get(args[0])? {
+        Object::Symbol(symbol) => symbol.0,
+        _ => return Err(Error::Type),
+      };
+      let env = args.get(1).cloned().unwrap_or(env);
+      let flag = match nat {
+        &Nat::IsBound => find(&name, env, lisp)?.is_some(),
+        _ => undefine(env, &name, lisp)?,
+      };
+      let result = lisp.heap.put(Object::Bool(flag))?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::CountLookups | &Nat::LookupCount => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let name = match lisp.heap.get(args[0])? {
+        Object::Symbol(symbol) => symbol.0,
+        _ => return Err(Error::Type),
+      };
+      let result = match nat {
+        &Nat::CountLookups => {
+          lisp.lookup_counts.insert(name, 0);
+          lisp.unit()?
+        }
+        _ => match lisp.lookup_counts.get(&name).cloned() {
+          Some(count) => lisp.number(count as i64)?,
+          None => lisp.f()?,
+        },
+      };
+      return Ok(Step::Return(result));
+    }
+    &Nat::EnvBindings => {
+      let args = lisp.args(value)?;
+      guard(args.len() <= 2)?;
+      let env = args.first().cloned().unwrap_or(env);
+      let sorted = match args.get(1) {
+        Some(&flag) => lisp.is_true(flag)?,
+        None => false,
+      };
+      let mut alist = vec![];
+      for (name, value) in lisp.bindings(env, sorted)? {
+        alist.push(lisp.pair(name, value)?);
+      }
+      let result = lisp.list(&alist)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::EnvChain => {
+      let args = lisp.args(value)?;
+      guard(args.len() <= 1)?;
+      let mut chain = vec![];
+      let mut next = Some(args.first().cloned().unwrap_or(env));
+      while let Some(pointer) = next {
+        next = match lisp.heap.get(pointer)? {
+          Object::Env(value) => value.parent,
+          _ => return Err(Error::Type),
+        };
+        chain.push(pointer);
+      }
+      let result = lisp.list(&chain)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::WriteFile => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let path = match lisp.heap.get(args[0])? {
+        Object::Str(path) => path,
+        _ => return Err(Error::Type),
+      };
+      let mut buf = String::new();
+      lisp.show(args[1], &mut buf)?;
+      buf.push('\n');
+      std::fs::write(&*path, buf).map_err(io_error)?;
+      let unit = lisp.unit()?;
+      return Ok(Step::Return(unit));
+    }
+    &Nat::Diff => {
+      let args = lisp.args(value)?;
+      arity(&args, 2)?;
+      let result = match diff(args[0], args[1], lisp)? {
+        Some(text) => lisp.heap.put(Object::Str(Rc::from(text.as_str())))?,
+        None => lisp.f()?,
+      };
+      return Ok(Step::Return(result));
+    }
+    &Nat::FromJson => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let src = match lisp.heap.get(args[0])? {
+        Object::Str(src) => src,
+        _ => return Err(Error::Type),
+      };
+      let result = lisp.read_json(&src)?;
+      return Ok(Step::Return(result));
+    }
+    &Nat::Eval => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty() && args.len() <= 2)?;
+      let env = args.get(1).cloned().unwrap_or(env);
+      if let Object::Env(_) = lisp.heap.get(env)? {
+        return Ok(Step::Eval(args[0], env));
+      }
+      return Err(Error::Type);
+    }
+    &Nat::TraceEval => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty() && args.len() <= 2)?;
+      let env = args.get(1).cloned().unwrap_or(env);
+      if let Object::Env(_) = lisp.heap.get(env)? {
+        let trace = lisp.trace_id;
+        lisp.trace_id += 1;
+        lisp.traces.insert(trace, vec![]);
+        lisp.stack.push(Frame::TraceEval { trace: trace });
+        return Ok(Step::Eval(args[0], env));
+      }
+      return Err(Error::Type);
+    }
+    &Nat::Trace => {
+      let args = lisp.args(value)?;
+      arity(&args, 1)?;
+      let key = match lisp.heap.get(args[0])? {
+        Object::Symbol(symbol) => symbol.0,
+        _ => return Err(Error::Type),
+      };
+      let owner = binding_env(&key, env, lisp)?.ok_or_else(|| Error::Unbound(key.clone()))?;
+      let proc = lookup(&key, owner, lisp)?;
+      let inner = combiner(proc, lisp)?;
+      if let Object::Proc(Proc::Traced(_)) = lisp.heap.get(inner)? {
+        return Ok(Step::Return(lisp.unit()?));
+      }
+      let traced = lisp.heap.put(Object::Proc(Proc::Traced(Traced { name: args[0], proc: inner })))?;
+      let traced = match lisp.heap.get(proc)? {
+        Object::Proc(Proc::App(_)) => lisp.heap.put(Object::Proc(Proc::App(App(traced))))?,
+        _ => traced,
+      };
+      define(owner, args[0], traced, lisp)?;
+      return Ok(Step::Return(lisp.unit()?));
+    }
+    &Nat::Init => {
+      let args = lisp.args(value)?;
+      arity(&args, 0)?;
+      let capabilities = lisp.capabilities;
+      let env = init_env(lisp, capabilities)?;
+      return Ok(Step::Return(env));
+    }
+    &Nat::Compile => {
+      let args = lisp.args(value)?;
+      guard(!args.is_empty() && args.len() <= 2)?;
+      let env = args.get(1).cloned().unwrap_or(env);
+      let compiled = lisp.compile(args[0], env)?;
+      return Ok(Step::Return(compiled));
+    }
+  }
+}
+
+enum Walk {
+  Visit(Gc),
+  Build(Gc),
+}
+
+/// Rebuild `tree` with `proc` applied to every atom. Pairs and the
+/// unit terminators of lists are structure, everything else is an
+/// atom. The walk keeps its own stack so deep trees don't overflow the
+/// Rust stack, and shared substructure is mapped once and stays
+/// shared. A pair that contains itself has no finite image, so cyclic
+/// input is an `Error::Guard` rather than a fixpoint.
+fn tree_map(proc: Gc, tree: Gc, env: Gc, lisp: &mut V0) -> Result<Gc> {
+  let mut done: HashMap<Gc, Gc> = HashMap::new();
+  let mut active: HashSet<Gc> = HashSet::new();
+  let mut results = vec![];
+  let mut walk = vec![Walk::Visit(tree)];
+  while let Some(item) = walk.pop() {
+    match item {
+      Walk::Visit(pointer) => {
+        if let Some(value) = done.get(&pointer) {
+          results.push(*value);
+          continue;
+        }
+        match lisp.heap.get(pointer)? {
+          Object::Pair(ref pair) => {
+            guard(active.insert(pointer))?;
+            walk.push(Walk::Build(pointer));
+            walk.push(Walk::Visit(pair.snd));
+            walk.push(Walk::Visit(pair.fst));
+          }
+          Object::Unit => {
+            results.push(pointer);
+          }
+          _ => {
+            let unit = lisp.unit()?;
+            let args = lisp.pair(pointer, unit)?;
+            let value = lisp.call(proc, args, env)?;
+            results.push(value);
+          }
+        }
+      }
+      Walk::Build(pointer) => {
+        let snd = results.pop().unwrap();
+        let fst = results.pop().unwrap();
+        let pair = lisp.pair(fst, snd)?;
+        active.remove(&pointer);
+        done.insert(pointer, pair);
+        results.push(pair);
+      }
+    }
+  }
+  return Ok(results.pop().unwrap());
+}
+
+/// Groups of built-ins an environment can be given, for embedders who
+/// want hosted code to do only so much; see `init_with`. Built-ins in
+/// no group, like `$vau`, `$if`, `eq?` and the type predicates, are
+/// always there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+  /// Numeric operations and comparisons.
+  pub arithmetic: bool,
+  /// Pairs and the list operations.
+  pub lists: bool,
+  /// String operations and string builders.
+  pub strings: bool,
+  /// Files, ports and the console.
+  pub io: bool,
+  /// `call/cc`, `call/ec`, `shift`, `reset` and `dynamic-wind`.
+  pub continuations: bool,
+}
+
+impl Capabilities {
+  pub const ALL: Capabilities = Capabilities {
+    arithmetic: true,
+    lists: true,
+    strings: true,
+    io: true,
+    continuations: true,
+  };
+
+  pub const NONE: Capabilities = Capabilities {
+    arithmetic: false,
+    lists: false,
+    strings: false,
+    io: false,
+    continuations: false,
+  };
+
+  /// Whether an environment with these capabilities gets `nat`.
+  fn allows(&self, nat: &Nat) -> bool {
+    match nat {
+      &Nat::Add | &Nat::Sub | &Nat::Mul | &Nat::Div | &Nat::Rem |
+      &Nat::Neg | &Nat::Abs_ | &Nat::Numerator | &Nat::Denominator |
+      &Nat::ExactToInexact | &Nat::InexactToExact |
+      &Nat::NumEq | &Nat::NumLt | &Nat::NumGt | &Nat::NumLe | &Nat::NumGe => {
+        return self.arithmetic;
+      }
+      &Nat::Pair | &Nat::Fst | &Nat::Snd | &Nat::SetFst |
+      &Nat::Cons | &Nat::Car | &Nat::Cdr | &Nat::ListProc |
+      &Nat::Length | &Nat::Append | &Nat::Reverse | &Nat::Map |
+      &Nat::Filter | &Nat::Foldl | &Nat::Foldr | &Nat::TreeMap |
+      &Nat::ProperTail | &Nat::IsNull | &Nat::IsPair |
+      &Nat::ListToVector | &Nat::VectorToList => {
+        return self.lists;
+      }
+      &Nat::StringLength | &Nat::StringRef | &Nat::StringAppend |
+      &Nat::Substring | &Nat::StringToSymbol | &Nat::SymbolToString |
+      &Nat::NumberToString | &Nat::StringToNumber |
+      &Nat::StringUpcase | &Nat::StringDowncase |
+      &Nat::StringEq | &Nat::StringLt |
+      &Nat::SbNew | &Nat::SbAppend | &Nat::SbBuild |
+      &Nat::Utf8Decode | &Nat::Utf8Encode => {
+        return self.strings;
+      }
+      &Nat::ReadFile | &Nat::WriteFile |
+      &Nat::OpenInputFile | &Nat::OpenOutputFile | &Nat::ClosePort |
+      &Nat::ReadChar | &Nat::PeekChar | &Nat::WriteChar |
+      &Nat::WriteString | &Nat::IsEof | &Nat::Trace => {
+        return self.io;
+      }
+      &Nat::CallCc | &Nat::CallEc | &Nat::DynamicWind => {
+        return self.continuations;
+      }
+      _ => {
+        return true;
+      }
+    }
+  }
+}
+
+/// A fresh environment with the built-ins `capabilities` allows bound
+/// and nothing else; the global environment starts as one, and `(init)`
+/// makes more, with the same capabilities.
+fn init_env(lisp: &mut V0, capabilities: Capabilities) -> Result<Gc> {
+  let env = lisp.env_new(None)?;
+  let operatives = [
+    ("$vau", Nat::Vau),
+    ("$lambda", Nat::Lambda),
+    ("$define!", Nat::Define),
+    ("$if", Nat::If),
+    ("$sequence", Nat::Sequence),
+    ("$quote", Nat::Quote),
+    ("receive", Nat::Receive),
+    ("guard", Nat::Guard),
+    ("parameterize", Nat::Parameterize),
+    ("define-syntax", Nat::DefineSyntax),
+    ("syntax-rules", Nat::SyntaxRules),
+    ("do", Nat::Do),
+    ("define-module", Nat::DefineModule),
+    ("export", Nat::Export),
+    ("import", Nat::Import),
+    ("trace", Nat::Trace),
+  ];
+  for &(name, ref nat) in operatives.iter() {
+    if lisp.sandboxed && UNSAFE.contains(&name) || !capabilities.allows(nat) {
+      continue;
+    }
+    let proc = lisp.heap.put(Object::Proc(Proc::Nat(nat.clone())))?;
+    let name = lisp.symbol(Rc::from(name))?;
+    define(env, name, proc, lisp)?;
+  }
+  let applicatives = [
+    ("pair", Nat::Pair),
+    ("fst", Nat::Fst),
+    ("snd", Nat::Snd),
+    ("set-fst!", Nat::SetFst),
+    ("freeze!", Nat::Freeze),
+    ("value-size", Nat::ValueSize),
+    ("store-put!", Nat::StorePut),
+    ("store-ref", Nat::StoreRef),
+    ("canonicalize", Nat::Canonicalize),
+    ("sandbox-eval", Nat::SandboxEval),
+    ("eval", Nat::Eval),
+    ("trace-eval", Nat::TraceEval),
+    ("init", Nat::Init),
+    ("and", Nat::And),
+    ("or", Nat::Or),
+    ("not", Nat::Not),
+    ("wrap", Nat::Wrap),
+    ("unwrap", Nat::Unwrap),
+    ("tree-map", Nat::TreeMap),
+    ("intern-stats", Nat::InternStats),
+    ("+", Nat::Add),
+    ("-", Nat::Sub),
+    ("*", Nat::Mul),
+    ("/", Nat::Div),
+    ("remainder", Nat::Rem),
+    ("negate", Nat::Neg),
+    ("abs", Nat::Abs_),
+    ("numerator", Nat::Numerator),
+    ("denominator", Nat::Denominator),
+    ("exact->inexact", Nat::ExactToInexact),
+    ("inexact->exact", Nat::InexactToExact),
+    ("validate-heap", Nat::ValidateHeap),
+    ("=", Nat::NumEq),
+    ("<", Nat::NumLt),
+    (">", Nat::NumGt),
+    ("<=", Nat::NumLe),
+    (">=", Nat::NumGe),
+    ("call-with-current-continuation", Nat::CallCc),
+    ("call/cc", Nat::CallCc),
+    ("dynamic-wind", Nat::DynamicWind),
+    ("make-parameter", Nat::MakeParameter),
+    ("cons", Nat::Cons),
+    ("car", Nat::Car),
+    ("cdr", Nat::Cdr),
+    ("list", Nat::ListProc),
+    ("length", Nat::Length),
+    ("append", Nat::Append),
+    ("reverse", Nat::Reverse),
+    ("map", Nat::Map),
+    ("filter", Nat::Filter),
+    ("foldl", Nat::Foldl),
+    ("foldr", Nat::Foldr),
+    ("call-with-escape-continuation", Nat::CallEc),
+    ("call/ec", Nat::CallEc),
+    ("eq?", Nat::Eq),
+    ("eqv?", Nat::Eqv),
+    ("equal?", Nat::Equal),
+    ("proper-tail?", Nat::ProperTail),
+    ("null?", Nat::IsNull),
+    ("pair?", Nat::IsPair),
+    ("symbol?", Nat::IsSymbol),
+    ("boolean?", Nat::IsBool),
+    ("number?", Nat::IsNumber),
+    ("procedure?", Nat::IsProc),
+    ("environment?", Nat::IsEnv),
+    ("apply", Nat::Apply),
+    ("make-operative", Nat::MakeOperative),
+    ("make-applicative", Nat::MakeApplicative),
+    ("vector?", Nat::IsVector),
+    ("make-vector", Nat::MakeVector),
+    ("vector-ref", Nat::VectorRef),
+    ("vector-set!", Nat::VectorSet),
+    ("vector-length", Nat::VectorLength),
+    ("vector-fill!", Nat::VectorFill),
+    ("list->vector", Nat::ListToVector),
+    ("vector->list", Nat::VectorToList),
+    ("procedure-source", Nat::ProcedureSource),
+    ("string?", Nat::IsString),
+    ("char?", Nat::IsChar),
+    ("value->json", Nat::ToJson),
+    ("json->value", Nat::FromJson),
+    ("diff", Nat::Diff),
+    ("read-file", Nat::ReadFile),
+    ("write-file", Nat::WriteFile),
+    ("environment-chain", Nat::EnvChain),
+    ("bound?", Nat::IsBound),
+    ("unbind!", Nat::Unbind),
+    ("environment-bindings", Nat::EnvBindings),
+    ("count-lookups!", Nat::CountLookups),
+    ("lookup-count", Nat::LookupCount),
+    ("compile", Nat::Compile),
+    ("bytevector?", Nat::IsBytevector),
+    ("hash-table?", Nat::IsHashTable),
+    ("make-bytevector", Nat::MakeBytevector),
+    ("bytevector", Nat::Bytevector),
+    ("bytevector-length", Nat::BytevectorLength),
+    ("bytevector-u8-ref", Nat::BytevectorU8Ref),
+    ("bytevector-u8-set!", Nat::BytevectorU8Set),
+    ("bytevector-copy", Nat::BytevectorCopy),
+    ("utf8->string", Nat::Utf8Decode),
+    ("string->utf8", Nat::Utf8Encode),
+    ("base64-encode", Nat::Base64Encode),
+    ("base64-decode", Nat::Base64Decode),
+    ("hash-bytes", Nat::HashBytes),
+    ("string-length", Nat::StringLength),
+    ("string-ref", Nat::StringRef),
+    ("string-append", Nat::StringAppend),
+    ("substring", Nat::Substring),
+    ("string->symbol", Nat::StringToSymbol),
+    ("symbol->string", Nat::SymbolToString),
+    ("number->string", Nat::NumberToString),
+    ("string->number", Nat::StringToNumber),
+    ("string-upcase", Nat::StringUpcase),
+    ("string-downcase", Nat::StringDowncase),
+    ("string=?", Nat::StringEq),
+    ("string<?", Nat::StringLt),
+    ("sb-new", Nat::SbNew),
+    ("sb-append!", Nat::SbAppend),
+    ("sb-build", Nat::SbBuild),
+    ("make-hash-table", Nat::MakeHashTable),
+    ("hash-table-ref", Nat::HashRef),
+    ("hash-table-set!", Nat::HashSet),
+    ("hash-table-delete!", Nat::HashDelete),
+    ("hash-table-contains?", Nat::HashContains),
+    ("hash-table-keys", Nat::HashKeys),
+    ("hash-table-values", Nat::HashValues),
+    ("hash-table-size", Nat::HashSize),
+    ("open-input-file", Nat::OpenInputFile),
+    ("open-output-file", Nat::OpenOutputFile),
+    ("close-port", Nat::ClosePort),
+    ("read-char", Nat::ReadChar),
+    ("peek-char", Nat::PeekChar),
+    ("write-char", Nat::WriteChar),
+    ("write-string", Nat::WriteString),
+    ("eof-object?", Nat::IsEof),
+    ("collect-garbage", Nat::CollectGarbage),
+    ("reserve", Nat::Reserve),
+    ("gensym", Nat::Gensym),
+    ("values", Nat::Values),
+    ("call-with-values", Nat::CallWithValues),
+    ("raise", Nat::Raise),
+    ("raise-continuable", Nat::RaiseContinuable),
+    ("with-exception-handler", Nat::WithExceptionHandler),
+  ];
+  for &(name, ref nat) in applicatives.iter() {
+    if lisp.sandboxed && UNSAFE.contains(&name) || !capabilities.allows(nat) {
+      continue;
+    }
+    let proc = lisp.heap.put(Object::Proc(Proc::Nat(nat.clone())))?;
+    let app = lisp.heap.put(Object::Proc(Proc::App(App(proc))))?;
+    let name = lisp.symbol(Rc::from(name))?;
+    define(env, name, app, lisp)?;
+  }
+  let ports = [
+    ("current-input-port", Port::new("stdin", Some(Box::new(std::io::stdin())), None)),
+    ("current-output-port", Port::new("stdout", None, Some(Box::new(std::io::stdout())))),
+  ];
+  for (name, port) in ports {
+    if lisp.sandboxed && UNSAFE.contains(&name) || !capabilities.io {
+      continue;
+    }
+    let port = lisp.heap.put(Object::Port(Rc::new(RefCell::new(port))))?;
+    let name = lisp.symbol(Rc::from(name))?;
+    define(env, name, port, lisp)?;
+  }
+  return Ok(env);
+}
+
+/// Built-ins a `sandbox` leaves out: file and console IO, `eval`,
+/// `trace-eval`, and everything else that could run data as code or
+/// reach a fresh environment to run it in.
+const UNSAFE: &[&str] = &[
+  "eval",
+  "trace-eval",
+  "compile",
+  "make-operative",
+  "make-applicative",
+  "init",
+  "trace",
+  "read-file",
+  "write-file",
+  "open-input-file",
+  "open-output-file",
+  "current-input-port",
+  "current-output-port",
+];
+
+/// How many nodes a `sandbox` heap can hold.
+const SANDBOX_CAPACITY: usize = 1 << 16;
+
+/// How long a vector, bytevector, string or string builder a `sandbox`
+/// makes can be, counting elements or bytes. One node can hold any
+/// amount, so `SANDBOX_CAPACITY` alone doesn't bound memory.
+const SANDBOX_LENGTH: usize = 1 << 16;
+
+const SMALL_MIN: i64 = -128;
+const SMALL_MAX: i64 = 255;
+
+pub struct V0 {
+  heap: Heap,
+  env: Gc,
+  stack: Vec<Frame>,
+  small: Vec<Gc>,
+  /// The `list` built-in, which `do` and `parameterize` hand to
+  /// `evlis` to gather the values they evaluate, made once rather than
+  /// on every use.
+  list_proc: Gc,
+  /// Weak, so a symbol nothing refers to anymore can be collected and
+  /// interned afresh next time it's read.
+  symbols: HashMap<Rc<str>, WeakGc>,
+  /// How often `symbol` found a name already interned, and didn't.
+  intern_hits: usize,
+  intern_misses: usize,
+  /// How often `number` found its value in `small`, and didn't.
+  small_hits: usize,
+  small_misses: usize,
+  runs: Vec<Run>,
+  run_id: usize,
+  escape: Option<Escape>,
+  extent_id: usize,
+  /// The `dynamic-wind`s control is inside, outermost first.
+  winds: Vec<Wind>,
+  /// Each parameter `parameterize` has bound and its value, innermost
+  /// last; see `Wind`.
+  params: Vec<(Gc, Gc)>,
+  /// The names macro expansions have introduced, by what they were
+  /// renamed to; see `Alias`. An entry goes once its symbol has been
+  /// collected.
+  aliases: HashMap<Rc<str>, Alias>,
+  alias_id: usize,
+  /// The environment of each module's exported bindings, by name;
+  /// see `module_name`.
+  modules: HashMap<Rc<str>, Gc>,
+  /// The values `store_put` has taken, by id, and their ids by
+  /// `structural_hash`.
+  stored: Vec<Gc>,
+  store: HashMap<u64, Vec<usize>>,
+  /// How many symbols `gensym` has made.
+  gensym_id: usize,
+  /// Expressions evaluated so far.
+  steps: u64,
+  /// How many more expressions can be evaluated before `Error::Time`,
+  /// or `None` for no limit; see `call_with_budget`.
+  budget: Option<usize>,
+  /// The budget each outermost evaluation starts with.
+  step_limit: Option<usize>,
+  /// Whether this is a `sandbox`, whose environments leave out the
+  /// `UNSAFE` built-ins.
+  sandboxed: bool,
+  /// The groups of built-ins this interpreter's environments have.
+  capabilities: Capabilities,
+  /// How to show foreign values, by the name of their type.
+  printers: HashMap<Rc<str>, Printer>,
+  /// The evaluation `debug_eval` has paused, if there is one.
+  debugger: Option<Debugger>,
+  pause_id: usize,
+  /// What each `trace-eval` still running has seen so far, by the id
+  /// its frames refer to.
+  traces: HashMap<usize, Vec<TraceNode>>,
+  trace_id: usize,
+  /// Called with each expression before it's evaluated; see
+  /// `set_trace_hook`.
+  trace_hook: Option<TraceHook>,
+  /// Where calls to a procedure given to `trace` are written; see
+  /// `set_trace_writer`.
+  trace_writer: Box<dyn Write>,
+  /// How `equal?` compares foreign values, by the name of their type.
+  equalities: HashMap<Rc<str>, Equality>,
+  /// How often each symbol given to `count-lookups!` has been
+  /// evaluated since. Empty unless something is being counted.
+  lookup_counts: HashMap<Rc<str>, usize>,
+  /// Bindings found through each parent environment, by name; see
+  /// `lookup`. `None` when the cache is off.
+  lookup_cache: Option<HashMap<Rc<str>, HashMap<Gc, Place>>>,
+  /// Let `]` close `(` and `)` close `[`, as before brackets had to match.
+  loose_brackets: bool,
+  /// Operator names some compiled code was compiled against, and how
+  /// many times one of them has been bound or unbound since; see
+  /// `compile`.
+  compile_names: HashSet<Rc<str>>,
+  /// Variable names some compiled code has the address of; see
+  /// `compile`.
+  compile_vars: HashSet<Rc<str>>,
+  compile_epoch: u64,
+  /// The operator each call site found last, unless it's off; see
+  /// `operator`.
+  call_cache: Option<HashMap<Gc, CallSite>>,
+  /// How many times each name some call site has cached has since been
+  /// bound or unbound anywhere. A fresh binding can shadow, and an
+  /// unbinding expose, an outer one, but a binding changed in place
+  /// needs no new version, since the cache holds the place.
+  versions: HashMap<Rc<str>, Rc<Cell<u64>>>,
+  /// Whether evaluation starts incremental collections itself; see
+  /// `set_incremental`.
+  incremental: bool,
+  /// Whether one it started may still be running.
+  collecting: bool,
+  /// The heap time before which `bounded_safe_point` won't collect.
+  next_collection: usize,
+  /// The forms `load_string` is evaluating and the values they've had
+  /// so far, kept alive for a collection partway through.
+  loading: Vec<Gc>,
+  /// Values the host has pinned with `root`, one slot per live guard.
+  /// A guard reads its value back from its slot, since `compact` can
+  /// move it.
+  pinned: Rc<RefCell<Vec<Option<Gc>>>>,
+}
+
+/// Keeps a value alive across collections until it's dropped.
+pub struct RootGuard {
+  slot: usize,
+  pinned: Rc<RefCell<Vec<Option<Gc>>>>,
+}
+
+impl RootGuard {
+  pub fn value(&self) -> Gc {
+    return self.pinned.borrow()[self.slot].unwrap();
+  }
+}
+
+impl Drop for RootGuard {
+  fn drop(&mut self) {
+    self.pinned.borrow_mut()[self.slot] = None;
+  }
+}
+
+fn show_string(value: &str, buf: &mut String) {
+  buf.push('"');
+  for rune in value.chars() {
+    match rune {
+      '"' => { buf.push_str("\\\"") }
+      '\\' => { buf.push_str("\\\\") }
+      '\n' => { buf.push_str("\\n") }
+      '\t' => { buf.push_str("\\t") }
+      '\r' => { buf.push_str("\\r") }
+      _ => { buf.push(rune) }
+    }
+  }
+  buf.push('"');
+}
+
+fn json_string(value: &str, buf: &mut String) {
+  buf.push('"');
+  for rune in value.chars() {
+    match rune {
+      '"' => { buf.push_str("\\\"") }
+      '\\' => { buf.push_str("\\\\") }
+      '\n' => { buf.push_str("\\n") }
+      '\t' => { buf.push_str("\\t") }
+      '\r' => { buf.push_str("\\r") }
+      _ if (rune as u32) < 0x20 => { buf.push_str(&format!("\\u{:04x}", rune as u32)) }
+      _ => { buf.push(rune) }
+    }
+  }
+  buf.push('"');
+}
+
+/// A symbol or string as a key in an `Object::HashMap`, marked with
+/// which it was so the two never collide.
+fn hash_key(key: Gc, lisp: &V0) -> Result<String> {
+  match lisp.heap.get(key)? {
+    Object::Symbol(symbol) => return Ok(format!("'{}", symbol.0)),
+    Object::Str(text) => return Ok(format!("\"{}", text)),
+    _ => return Err(Error::Type),
+  }
+}
+
+fn sorted_entries(table: &HashMap<String, Gc>) -> Vec<(String, Gc)> {
+  let mut entries: Vec<(String, Gc)> = table.iter().map(|(key, &value)| (key.clone(), value)).collect();
+  entries.sort_by(|a, b| a.0.cmp(&b.0));
+  return entries;
+}
+
+/// The 64-bit FNV-1a hash of `bytes`: fast and stable across runs and
+/// platforms, but easy to collide on purpose, so not for untrusted
+/// input where that matters.
+fn fnv1a(bytes: &[u8]) -> u64 {
+  return fnv1a_extend(0xcbf29ce484222325, bytes);
+}
+
+/// Carry on the FNV-1a `hash` of some bytes with more of them.
+fn fnv1a_extend(mut hash: u64, bytes: &[u8]) -> u64 {
+  for &byte in bytes.iter() {
+    hash ^= byte as u64;
+    hash = hash.wrapping_mul(0x100000001b3);
+  }
+  return hash;
+}
+
+/// How many nodes `structural_hash` looks at, at most, which is what
+/// makes it finish on cyclic structure.
+const HASH_NODES: usize = 4096;
+
+/// A hash of `value` that's the same for any two `equal?` values,
+/// made from its first `HASH_NODES` nodes in depth-first order. It's
+/// a type error for anything but data, and a guard error for a pair,
+/// vector or bytevector that isn't frozen.
+fn structural_hash(value: Gc, lisp: &V0) -> Result<u64> {
+  let mut hash = fnv1a(&[]);
+  let mut todo = vec![value];
+  let mut count = 0;
+  while let Some(pointer) = todo.pop() {
+    if count == HASH_NODES {
+      break;
+    }
+    count += 1;
+    let object = lisp.heap.get(pointer)?;
+    match object {
+      Object::Pair(_) | Object::Vector(_) | Object::Bytevector(_) => {
+        guard(lisp.heap.is_frozen(pointer))?;
+      }
+      _ => {}
+    }
+    match object {
+      Object::Unit => {
+        hash = fnv1a_extend(hash, &[0]);
+      }
+      Object::Bool(flag) => {
+        hash = fnv1a_extend(hash, &[1, flag as u8]);
+      }
+      Object::Number(value) => {
+        hash = fnv1a_extend(hash, &[2]);
+        hash = fnv1a_extend(hash, &value.to_le_bytes());
+      }
+      Object::Rational { numer, denom } => {
+        hash = fnv1a_extend(hash, &[3]);
+        hash = fnv1a_extend(hash, &numer.to_le_bytes());
+        hash = fnv1a_extend(hash, &denom.to_le_bytes());
+      }
+      #[cfg(feature = "bignum")]
+      Object::BigInt(ref value) => {
+        hash = fnv1a_extend(hash, &[4]);
+        hash = fnv1a_extend(hash, value.to_string().as_bytes());
+      }
+      #[cfg(feature = "bignum")]
+      Object::BigRational { ref numer, ref denom } => {
+        hash = fnv1a_extend(hash, &[5]);
+        hash = fnv1a_extend(hash, format!("{}/{}", numer, denom).as_bytes());
+      }
+      Object::Str(ref text) => {
+        hash = fnv1a_extend(hash, &[6]);
+        hash = fnv1a_extend(hash, &text.len().to_le_bytes());
+        hash = fnv1a_extend(hash, text.as_bytes());
+      }
+      Object::Symbol(ref symbol) => {
+        hash = fnv1a_extend(hash, &[7]);
+        hash = fnv1a_extend(hash, &symbol.0.len().to_le_bytes());
+        hash = fnv1a_extend(hash, symbol.0.as_bytes());
+      }
+      Object::Pair(ref pair) => {
+        hash = fnv1a_extend(hash, &[8]);
+        todo.push(pair.snd);
+        todo.push(pair.fst);
+      }
+      Object::Vector(ref values) => {
+        hash = fnv1a_extend(hash, &[9]);
+        hash = fnv1a_extend(hash, &values.borrow().len().to_le_bytes());
+        todo.extend(values.borrow().iter().rev());
+      }
+      Object::Bytevector(ref bytes) => {
+        hash = fnv1a_extend(hash, &[10]);
+        hash = fnv1a_extend(hash, &bytes.borrow().len().to_le_bytes());
+        hash = fnv1a_extend(hash, &bytes.borrow());
+      }
+      Object::Char(rune) => {
+        hash = fnv1a_extend(hash, &[11]);
+        hash = fnv1a_extend(hash, &(rune as u32).to_le_bytes());
+      }
+      _ => {
+        return Err(Error::Type);
+      }
+    }
+  }
+  return Ok(hash);
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard, padded base64.
+fn base64_encode(bytes: &[u8]) -> String {
+  let mut buf = String::with_capacity(bytes.len().div_ceil(3) * 4);
+  for chunk in bytes.chunks(3) {
+    let mut word = 0u32;
+    for (index, &byte) in chunk.iter().enumerate() {
+      word |= (byte as u32) << (16 - 8 * index);
+    }
+    for index in 0..4 {
+      if index <= chunk.len() {
+        buf.push(BASE64[(word >> (18 - 6 * index)) as usize & 63] as char);
+      } else {
+        buf.push('=');
+      }
+    }
+  }
+  return buf;
+}
+
+/// The bytes `text` encodes, if it's padded base64 with nothing else
+/// in it, not even whitespace.
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+  let text = text.as_bytes();
+  if !text.len().is_multiple_of(4) {
+    return None;
+  }
+  let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+  for (at, chunk) in text.chunks(4).enumerate() {
+    let last = at == text.len() / 4 - 1;
+    let pad = chunk.iter().rev().take_while(|&&rune| rune == b'=').count();
+    if pad > 2 || (pad > 0 && !last) {
+      return None;
+    }
+    let mut word = 0u32;
+    for (index, rune) in chunk[..4 - pad].iter().enumerate() {
+      let digit = BASE64.iter().position(|x| x == rune)? as u32;
+      word |= digit << (18 - 6 * index);
+    }
+    if word & ((1 << (8 * pad)) - 1) != 0 {
+      return None;
+    }
+    for index in 0..3 - pad {
+      bytes.push((word >> (16 - 8 * index)) as u8);
+    }
+  }
+  return Some(bytes);
+}
+
+struct JsonReader {
+  src: Vec<char>,
+  index: usize,
+}
+
+impl JsonReader {
+  fn skip(&mut self) {
+    while let Some(' ') | Some('\t') | Some('\r') | Some('\n') = self.src.get(self.index) {
+      self.index += 1;
+    }
+  }
+
+  fn peek(&mut self) -> Result<char> {
+    self.skip();
+    return self.src.get(self.index).cloned().ok_or(Error::Read);
+  }
+
+  fn expect(&mut self, rune: char) -> Result<()> {
+    if self.peek()? != rune {
+      return Err(Error::Read);
+    }
+    self.index += 1;
+    return Ok(());
+  }
+
+  fn keyword(&mut self, word: &str) -> Result<()> {
+    for rune in word.chars() {
+      if self.src.get(self.index) != Some(&rune) {
+        return Err(Error::Read);
+      }
+      self.index += 1;
+    }
+    return Ok(());
+  }
+
+  fn string(&mut self) -> Result<String> {
+    self.expect('"')?;
+    let mut buf = String::new();
+    loop {
+      let rune = *self.src.get(self.index).ok_or(Error::Read)?;
+      self.index += 1;
+      match rune {
+        '"' => { break }
+        '\\' => {
+          let rune = *self.src.get(self.index).ok_or(Error::Read)?;
+          self.index += 1;
+          match rune {
+            '"' | '\\' | '/' => { buf.push(rune) }
+            'b' => { buf.push('\u{8}') }
+            'f' => { buf.push('\u{c}') }
+            'n' => { buf.push('\n') }
+            'r' => { buf.push('\r') }
+            't' => { buf.push('\t') }
+            'u' => {
+              let digits: String = self.src.get(self.index..self.index + 4)
+                .ok_or(Error::Read)?.iter().collect();
+              self.index += 4;
+              let code = u32::from_str_radix(&digits, 16).map_err(|_| Error::Read)?;
+              buf.push(std::char::from_u32(code).ok_or(Error::Read)?);
+            }
+            _ => { return Err(Error::Read) }
+          }
+        }
+        _ => {
+          buf.push(rune);
+        }
+      }
+    }
+    return Ok(buf);
+  }
+
+  fn items(&mut self, close: char, lisp: &mut V0) -> Result<Vec<Gc>> {
+    let mut items = vec![];
+    if self.peek()? == close {
+      self.index += 1;
+      return Ok(items);
+    }
+    loop {
+      items.push(self.value(lisp)?);
+      match self.peek()? {
+        ',' => { self.index += 1 }
+        rune if rune == close => {
+          self.index += 1;
+          return Ok(items);
+        }
+        _ => { return Err(Error::Read) }
+      }
+    }
+  }
+
+  fn value(&mut self, lisp: &mut V0) -> Result<Gc> {
+    match self.peek()? {
+      'n' => {
+        self.keyword("null")?;
+        return lisp.unit();
+      }
+      't' => {
+        self.keyword("true")?;
+        return lisp.t();
+      }
+      'f' => {
+        self.keyword("false")?;
+        return lisp.f();
+      }
+      '"' => {
+        let value = self.string()?;
+        return lisp.heap.put(Object::Str(Rc::from(value.as_str())));
+      }
+      '[' => {
+        self.index += 1;
+        let items = self.items(']', lisp)?;
+        return lisp.list(&items);
+      }
+      '{' => {
+        self.index += 1;
+        let mut fields = vec![];
+        if self.peek()? == '}' {
+          self.index += 1;
+        } else {
+          loop {
+            let key = self.string()?;
+            self.expect(':')?;
+            let value = self.value(lisp)?;
+            fields.push((key, value));
+            match self.peek()? {
+              ',' => { self.index += 1 }
+              '}' => {
+                self.index += 1;
+                break;
+              }
+              _ => { return Err(Error::Read) }
+            }
+          }
+        }
+        return json_object(fields, lisp);
+      }
+      _ => {
+        let start = self.index;
+        while let Some(rune) = self.src.get(self.index) {
+          match rune {
+            '-' | '+' | '.' | 'e' | 'E' | '0'..='9' => { self.index += 1 }
+            _ => { break }
+          }
+        }
+        let digits: String = self.src[start..self.index].iter().collect();
+        let value = digits.parse::<i64>().map_err(|_| Error::Read)?;
+        return lisp.number(value);
+      }
+    }
+  }
+}
+
+/// The value a JSON object with `fields` reads as; see `read_json`.
+fn json_object(fields: Vec<(String, Gc)>, lisp: &mut V0) -> Result<Gc> {
+  if fields.len() == 1 {
+    let (ref key, value) = fields[0];
+    match (key.as_str(), lisp.heap.get(value)?) {
+      ("symbol", Object::Str(ref name)) => {
+        return lisp.symbol(name.clone());
+      }
+      ("char", Object::Str(ref text)) if text.chars().count() == 1 => {
+        let rune = text.chars().next().unwrap();
+        return lisp.heap.put(Object::Char(rune));
+      }
+      ("pair", Object::Pair(_)) => {
+        let xs = lisp.args(value)?;
+        if xs.len() == 2 {
+          return lisp.pair(xs[0], xs[1]);
+        }
+      }
+      ("vector", Object::Pair(_)) | ("vector", Object::Unit) => {
+        let xs = lisp.args(value)?;
+        return lisp.heap.put(Object::Vector(Rc::new(RefCell::new(xs))));
+      }
+      _ => {}
+    }
+  }
+  let mut alist = vec![];
+  for (key, value) in fields {
+    let key = lisp.heap.put(Object::Str(Rc::from(key.as_str())))?;
+    alist.push(lisp.pair(key, value)?);
+  }
+  return lisp.list(&alist);
+}
+
+/// A value on the heap of a `V0`, which serde serializes in the shape
+/// `to_json` writes; see `V0::serializable`.
+#[cfg(feature = "serde")]
+pub struct Serializable<'a> {
+  lisp: &'a V0,
+  value: Gc,
+  /// The pairs and vectors being serialized further out, which is how
+  /// a cycle is caught.
+  active: Rc<RefCell<HashSet<Gc>>>,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> Serializable<'a> {
+  fn child(&self, value: Gc) -> Self {
+    return Serializable { lisp: self.lisp, value: value, active: self.active.clone() };
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> serde::Serialize for Serializable<'a> {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    use serde::ser::{Error as _, SerializeMap, SerializeSeq};
+    let object = self.lisp.heap.get(self.value).map_err(S::Error::custom)?;
+    if let Object::Pair(_) | Object::Vector(_) = object {
+      if !self.active.borrow_mut().insert(self.value) {
+        return Err(S::Error::custom(Error::Guard));
+      }
+    }
+    let result = match object {
+      Object::Unit => serializer.serialize_unit(),
+      Object::Bool(flag) => serializer.serialize_bool(flag),
+      Object::Number(value) => serializer.serialize_i64(value),
+      #[cfg(feature = "bignum")]
+      Object::BigInt(ref value) => match value.to_i128() {
+        Some(value) => serializer.serialize_i128(value),
+        None => Err(S::Error::custom(Error::Type)),
+      },
+      Object::Str(ref value) => serializer.serialize_str(value),
+      Object::Symbol(ref value) => {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("symbol", &*value.0)?;
+        map.end()
+      }
+      Object::Char(rune) => {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("char", &rune)?;
+        map.end()
+      }
+      Object::Pair(ref pair) if pair.is_list => {
+        let mut seq = serializer.serialize_seq(None)?;
+        for x in self.lisp.list_iter(self.value) {
+          seq.serialize_element(&self.child(x.map_err(S::Error::custom)?))?;
+        }
+        seq.end()
+      }
+      Object::Pair(ref pair) => {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("pair", &(self.child(pair.fst), self.child(pair.snd)))?;
+        map.end()
+      }
+      Object::Vector(ref xs) => {
+        let xs: Vec<Serializable> = xs.borrow().iter().map(|&x| self.child(x)).collect();
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("vector", &xs)?;
+        map.end()
+      }
+      _ => Err(S::Error::custom(Error::Type)),
+    };
+    self.active.borrow_mut().remove(&self.value);
+    return result;
+  }
+}
+
+/// Deserializes a value onto the heap of a `V0`, reading what
+/// `read_json` would from the same JSON; see `V0::seed`.
+#[cfg(feature = "serde")]
+pub struct Seed<'a> {
+  lisp: &'a mut V0,
+}
+
+#[cfg(feature = "serde")]
+impl<'a, 'de> serde::de::DeserializeSeed<'de> for Seed<'a> {
+  type Value = Gc;
+
+  fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> std::result::Result<Gc, D::Error> {
+    return deserializer.deserialize_any(self);
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'a, 'de> serde::de::Visitor<'de> for Seed<'a> {
+  type Value = Gc;
+
+  fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    return write!(f, "null, a boolean, an integer, a string, an array or an object");
+  }
+
+  fn visit_unit<E: serde::de::Error>(self) -> std::result::Result<Gc, E> {
+    return self.lisp.unit().map_err(E::custom);
+  }
+
+  fn visit_none<E: serde::de::Error>(self) -> std::result::Result<Gc, E> {
+    return self.visit_unit();
+  }
+
+  fn visit_bool<E: serde::de::Error>(self, value: bool) -> std::result::Result<Gc, E> {
+    let result = if value { self.lisp.t() } else { self.lisp.f() };
+    return result.map_err(E::custom);
+  }
+
+  fn visit_i64<E: serde::de::Error>(self, value: i64) -> std::result::Result<Gc, E> {
+    return self.lisp.number(value).map_err(E::custom);
+  }
+
+  fn visit_u64<E: serde::de::Error>(self, value: u64) -> std::result::Result<Gc, E> {
+    let value = i64::try_from(value).map_err(|_| E::custom(Error::Read))?;
+    return self.visit_i64(value);
+  }
+
+  fn visit_str<E: serde::de::Error>(self, value: &str) -> std::result::Result<Gc, E> {
+    return self.lisp.heap.put(Object::Str(Rc::from(value))).map_err(E::custom);
+  }
+
+  fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Gc, A::Error> {
+    use serde::de::Error as _;
+    let mut items = vec![];
+    while let Some(item) = seq.next_element_seed(Seed { lisp: &mut *self.lisp })? {
+      items.push(item);
+    }
+    return self.lisp.list(&items).map_err(A::Error::custom);
+  }
+
+  fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> std::result::Result<Gc, A::Error> {
+    use serde::de::Error as _;
+    let mut fields = vec![];
+    while let Some(key) = map.next_key::<String>()? {
+      let value = map.next_value_seed(Seed { lisp: &mut *self.lisp })?;
+      fields.push((key, value));
+    }
+    return json_object(fields, self.lisp).map_err(A::Error::custom);
+  }
+}
+
+#[cfg(feature = "serde")]
+impl V0 {
+  /// `value`, for serde to serialize like `to_json` would, with the
+  /// same errors.
+  pub fn serializable(&self, value: Gc) -> Serializable<'_> {
+    return Serializable { lisp: self, value: value, active: Rc::new(RefCell::new(HashSet::new())) };
+  }
+
+  /// A serde seed that deserializes into a value on this heap, the way
+  /// `read_json` reads JSON.
+  pub fn seed(&mut self) -> Seed<'_> {
+    return Seed { lisp: self };
+  }
+}
+
+impl V0 {
+  /// Build a value from JSON, inverting `to_json`: null is unit, arrays
+  /// are lists, and the single-key {"symbol"}, {"pair"} and {"vector"}
+  /// objects are rebuilt as what they tag. Any other object becomes an
+  /// alist of (key . value) with string keys. Anything `to_json`
+  /// produces reads back with the same shape (`equal?` apart from
+  /// vectors, which come back fresh), but foreign JSON doesn't always
+  /// round-trip: an empty array reads as unit and so comes back as null.
+  /// Only integers are numbers, so fractions and exponents are
+  /// `Error::Read`, like any other malformed input.
+  fn read_json(&mut self, src: &str) -> Result<Gc> {
+    let mut reader = JsonReader { src: src.chars().collect(), index: 0 };
+    let value = reader.value(self)?;
+    reader.skip();
+    guard(reader.index == reader.src.len()).map_err(|_| Error::Read)?;
+    return Ok(value);
+  }
+
+  /// Render `value` as JSON. Unit is null, booleans and numbers are
+  /// themselves, strings are JSON strings, and a proper list is an
+  /// array. The other types are tagged objects: {"symbol": name},
+  /// {"pair": [fst, snd]} and {"vector": [...]}. Procedures and
+  /// environments have no JSON form and are `Error::Type`. Cyclic
+  /// structure is `Error::Guard`.
+  fn to_json(&self, value: Gc, buf: &mut String) -> Result<()> {
+    let mut active = HashSet::new();
+    return self.to_json_in(value, &mut active, buf);
+  }
+
+  fn to_json_in(&self, value: Gc, active: &mut HashSet<Gc>, buf: &mut String) -> Result<()> {
+    let object = self.heap.get(value)?;
+    if let Object::Pair(_) | Object::Vector(_) = object {
+      guard(active.insert(value))?;
+    }
+    match object {
+      Object::Unit => {
+        buf.push_str("null");
+      }
+      Object::Bool(flag) => {
+        buf.push_str(if flag { "true" } else { "false" });
+      }
+      Object::Number(value) => {
+        buf.push_str(&value.to_string());
+      }
+      #[cfg(feature = "bignum")]
+      Object::BigInt(ref value) => {
+        buf.push_str(&value.to_string());
+      }
+      Object::Str(ref value) => {
+        json_string(value, buf);
+      }
+      Object::Char(rune) => {
+        buf.push_str("{\"char\":");
+        json_string(rune.encode_utf8(&mut [0; 4]), buf);
+        buf.push('}');
+      }
+      Object::Symbol(ref value) => {
+        buf.push_str("{\"symbol\":");
+        json_string(&value.0, buf);
+        buf.push('}');
+      }
+      Object::Pair(ref pair) => {
+        if pair.is_list {
+          buf.push('[');
+          for (index, x) in self.list_iter(value).enumerate() {
+            if index > 0 {
+              buf.push(',');
+            }
+            self.to_json_in(x?, active, buf)?;
+          }
+          buf.push(']');
+        } else {
+          buf.push_str("{\"pair\":[");
+          self.to_json_in(pair.fst, active, buf)?;
+          buf.push(',');
+          self.to_json_in(pair.snd, active, buf)?;
+          buf.push_str("]}");
+        }
+      }
+      Object::Vector(ref xs) => {
+        buf.push_str("{\"vector\":[");
+        for (index, x) in xs.borrow().iter().enumerate() {
+          if index > 0 {
+            buf.push(',');
+          }
+          self.to_json_in(*x, active, buf)?;
+        }
+        buf.push_str("]}");
+      }
+      Object::Rational { .. } | Object::Proc(_) | Object::Env(_) | Object::Bytevector(_)
+      | Object::Port(_) | Object::Eof | Object::MultipleValues(_) | Object::Macro(_)
+      | Object::HashMap(_) | Object::Foreign(_) | Object::Builder(_) => {
+        return Err(Error::Type);
+      }
+      #[cfg(feature = "bignum")]
+      Object::BigRational { .. } => {
+        return Err(Error::Type);
+      }
+    }
+    active.remove(&value);
+    return Ok(());
+  }
+
+  fn new(capacity: usize) -> Result<Self> {
+    return V0::with_mode(capacity, None, false, Capabilities::ALL);
+  }
+
+  fn with_mode(
+    capacity: usize,
+    max_capacity: Option<usize>,
+    sandboxed: bool,
+    capabilities: Capabilities) -> Result<Self> {
+    let mut heap = Heap::with_capacity(capacity);
+    // Set before the base environment is built, so building it can't
+    // grow the heap past the limit either.
+    heap.max_capacity = max_capacity;
+    let frame = heap.put(Object::Unit)?;
+    let env = heap.put(Object::Env(Env { frame: frame, parent: None, slots: None }))?;
+    let list_proc = heap.put(Object::Proc(Proc::Nat(Nat::ListProc)))?;
+    let mut lisp = V0 {
+      heap: heap,
+      env: env,
+      stack: vec![],
+      small: vec![],
+      list_proc: list_proc,
+      symbols: HashMap::new(),
+      intern_hits: 0,
+      intern_misses: 0,
+      small_hits: 0,
+      small_misses: 0,
+      runs: vec![],
+      run_id: 0,
+      escape: None,
+      extent_id: 0,
+      winds: vec![],
+      params: vec![],
+      aliases: HashMap::new(),
+      alias_id: 0,
+      modules: HashMap::new(),
+      stored: vec![],
+      store: HashMap::new(),
+      gensym_id: 0,
+      steps: 0,
+      budget: None,
+      step_limit: None,
+      sandboxed: sandboxed,
+      capabilities: capabilities,
+      printers: HashMap::new(),
+      debugger: None,
+      pause_id: 0,
+      traces: HashMap::new(),
+      trace_id: 0,
+      trace_hook: None,
+      trace_writer: Box::new(std::io::stdout()),
+      equalities: HashMap::new(),
+      lookup_counts: HashMap::new(),
+      lookup_cache: None,
+      loose_brackets: false,
+      incremental: false,
+      collecting: false,
+      next_collection: 0,
+      compile_names: HashSet::new(),
+      compile_vars: HashSet::new(),
+      compile_epoch: 0,
+      call_cache: Some(HashMap::new()),
+      versions: HashMap::new(),
+      loading: vec![],
+      pinned: Rc::new(RefCell::new(vec![])),
+    };
+    // The one place numbers don't go through `number`: this is the
+    // table it hands out.
+    for value in SMALL_MIN..=SMALL_MAX {
+      let pointer = lisp.heap.put(Object::Number(value))?;
+      lisp.small.push(pointer);
+    }
+    lisp.env = init_env(&mut lisp, capabilities)?;
+    lisp.gc();
+    return Ok(lisp);
+  }
+
+  pub fn gc(&mut self) {
+    self.gc_with(&[]);
+  }
+
+  /// Make room for at least `capacity` nodes now, so a computation
+  /// known to need that many doesn't keep growing the heap as it goes.
+  pub fn reserve(&mut self, capacity: usize) -> Result<()> {
+    return self.heap.reserve(capacity);
+  }
+
+  /// The id of `value` in the content-addressed store, which is the
+  /// same for any two values that are `equal?`, adding it if it's new.
+  /// Only data can be stored, and its pairs, vectors and bytevectors
+  /// have to be frozen first, so an id always stands for the same
+  /// structure; a mutator given a stored value changes a copy. Stored
+  /// values are kept alive for good.
+  pub fn store_put(&mut self, value: Gc) -> Result<usize> {
+    let hash = structural_hash(value, self)?;
+    if let Some(ids) = self.store.get(&hash) {
+      for &id in ids.iter() {
+        if is_equal(self.stored[id], value, self)? {
+          return Ok(id);
+        }
+      }
+    }
+    let id = self.stored.len();
+    self.stored.push(value);
+    self.store.entry(hash).or_default().push(id);
+    return Ok(id);
+  }
+
+  /// The value stored under `id`; see `store_put`.
+  pub fn store_ref(&self, id: usize) -> Option<Gc> {
+    return self.stored.get(id).cloned();
+  }
+
+  /// A frozen copy of `value` whose every node is the one kept in the
+  /// content-addressed store, so two canonical values are `equal?` just
+  /// when they're `eq?`. Nodes that are already canonical are shared
+  /// rather than copied, and `value` itself is left alone. It's a type
+  /// error for anything but data, and a guard error for a cycle.
+  pub fn canonicalize(&mut self, value: Gc) -> Result<Gc> {
+    let mut done: HashMap<Gc, Gc> = HashMap::new();
+    let mut open = HashSet::new();
+    let mut todo = vec![(value, false)];
+    while let Some((pointer, expanded)) = todo.pop() {
+      if done.contains_key(&pointer) {
+        continue;
+      }
+      let object = self.heap.get(pointer)?;
+      let node = match object {
+        Object::Pair(_) | Object::Vector(_) if !expanded => {
+          guard(open.insert(pointer))?;
+          todo.push((pointer, true));
+          let mut children = vec![];
+          object.pointers(&mut children);
+          todo.extend(children.into_iter().map(|child| (child, false)));
+          continue;
+        }
+        Object::Pair(pair) => {
+          let fst = done[&pair.fst];
+          let snd = done[&pair.snd];
+          if self.heap.is_frozen(pointer) && fst == pair.fst && snd == pair.snd {
+            pointer
+          } else {
+            self.pair(fst, snd)?
+          }
+        }
+        Object::Vector(values) => {
+          let copy: Vec<Gc> = values.borrow().iter().map(|value| done[value]).collect();
+          if self.heap.is_frozen(pointer) && *values.borrow() == copy {
+            pointer
+          } else {
+            self.heap.put(Object::Vector(Rc::new(RefCell::new(copy))))?
+          }
+        }
+        Object::Bytevector(bytes) => {
+          if self.heap.is_frozen(pointer) {
+            pointer
+          } else {
+            self.heap.put(Object::Bytevector(Rc::new(RefCell::new(bytes.borrow().clone()))))?
+          }
+        }
+        _ => pointer,
+      };
+      open.remove(&pointer);
+      self.freeze(node)?;
+      let id = self.store_put(node)?;
+      done.insert(pointer, self.stored[id]);
+    }
+    return Ok(done[&value]);
+  }
+
+  /// Collect, also keeping `extra` alive.
+  fn gc_with(&mut self, extra: &[Gc]) {
+    while !self.heap.step(usize::MAX).unwrap() {}
+    let mut roots = extra.to_vec();
+    self.roots(&mut roots);
+    self.heap.mark(&roots).unwrap();
+    self.heap.sweep();
+    self.prune();
+  }
+
+  /// Forget symbols and cached places a collection may have freed.
+  fn prune(&mut self) {
+    let heap = &self.heap;
+    self.symbols.retain(|_, &mut weak| heap.upgrade(weak).is_some());
+    let symbols = &self.symbols;
+    self.aliases.retain(|name, _| symbols.contains_key(name));
+    self.clear_caches();
+  }
+
+  /// Do at most `work` node visits of an incremental collection,
+  /// starting one if none is running, and say whether it finished.
+  /// Allocation also advances a running collection a little, so a host
+  /// with a frame budget can start cycles here at safe points and let
+  /// the program's own allocation carry them along.
+  pub fn gc_step(&mut self, work: usize) -> bool {
+    if self.heap.is_idle() {
+      let mut roots = vec![];
+      self.roots(&mut roots);
+      self.heap.begin(&roots);
+    }
+    let done = self.heap.step(work).unwrap();
+    if done {
+      self.prune();
+    }
+    return done;
+  }
+
+  /// With this on, evaluation begins an incremental collection at a
+  /// safe point once the heap is three quarters full, and allocation
+  /// carries it along from there, so there's no one long pause when the
+  /// heap fills up. A cycle can begin in the middle of any `eval`, so a
+  /// host has to `root` whatever it holds across one, not just across
+  /// `gc`.
+  pub fn set_incremental(&mut self, on: bool) {
+    self.incremental = on;
+  }
+
+  /// Resolve `weak`, keeping the object alive for a collection in
+  /// progress now that it's reachable again.
+  pub fn upgrade(&mut self, weak: WeakGc) -> Option<Gc> {
+    let pointer = self.heap.upgrade(weak)?;
+    self.heap.shade(pointer);
+    return Some(pointer);
+  }
+
+  pub fn step_count(&self) -> u64 {
+    return self.steps;
+  }
+
+  /// Evaluate `expr` in `env`, failing with `Error::Time` if it takes
+  /// more than `steps` expressions. Inside another budget, it can only
+  /// use what's left of that one, and what it uses comes out of it.
+  pub fn call_with_budget(&mut self, expr: Gc, env: Gc, steps: usize) -> Result<Gc> {
+    if self.runs.is_empty() {
+      self.budget = self.step_limit;
+    }
+    let outer = self.budget;
+    let start = outer.map_or(steps, |outer| outer.min(steps));
+    self.budget = Some(start);
+    let result = run(Step::Eval(expr, env), self);
+    let used = start - self.budget.unwrap_or(0);
+    self.budget = outer.map(|outer| outer - used);
+    return result;
+  }
+
+  /// Start evaluating `value` in `env` a step at a time, and take the
+  /// first; see `debug_continue`. Any evaluation paused before is
+  /// dropped. In between steps the host can evaluate other things as
+  /// usual.
+  pub fn debug_eval(&mut self, value: Gc, env: Gc) -> Result<DebugStep> {
+    if let Some(debugger) = self.debugger.take() {
+      self.stack.truncate(debugger.base);
+      while self.winds.last().is_some_and(|wind| wind.depth >= debugger.base) {
+        self.unwind();
+      }
+    }
+    if self.runs.is_empty() {
+      self.budget = self.step_limit;
+    }
+    let id = self.run_id;
+    self.run_id += 1;
+    self.pause_id += 1;
+    let pause = self.pause_id;
+    self.debugger = Some(Debugger { step: Step::Eval(value, env), id: id, base: self.stack.len(), pause: pause });
+    return self.debug_step(pause);
+  }
+
+  /// Take the next step of the evaluation `step` is paused in. A
+  /// finished one stays finished, and it's `Error::Guard` if `step`
+  /// isn't the latest or an error has ended it.
+  pub fn debug_continue(&mut self, step: &DebugStep) -> Result<DebugStep> {
+    match *step {
+      DebugStep::Done(value) => return Ok(DebugStep::Done(value)),
+      DebugStep::Step { pause, .. } => return self.debug_step(pause),
+    }
+  }
+
+  /// Take the step the evaluation is paused at, if `pause` is where.
+  fn debug_step(&mut self, pause: usize) -> Result<DebugStep> {
+    guard(self.debugger.as_ref().is_some_and(|debugger| debugger.pause == pause))?;
+    let debugger = self.debugger.take().unwrap();
+    guard(self.stack.len() >= debugger.base)?;
+    self.runs.push(Run { id: debugger.id, base: debugger.base });
+    let next = advance(debugger.step, debugger.id, debugger.base, self);
+    self.runs.pop();
+    let next = match next? {
+      Advance::Done(value) => return Ok(DebugStep::Done(value)),
+      Advance::Next(next) => next,
+    };
+    let unit = self.unit()?;
+    let mut description = String::new();
+    let (result, next_value, next_env) = match next {
+      Step::Eval(value, env) => {
+        description.push_str("eval ");
+        self.show(value, &mut description)?;
+        (unit, value, env)
+      }
+      Step::Exec(body, env) => {
+        description.push_str("exec ");
+        self.show(body, &mut description)?;
+        (unit, body, env)
+      }
+      Step::Apply(proc, args, env) => {
+        description.push_str("apply ");
+        self.show(proc, &mut description)?;
+        description.push_str(" to ");
+        self.show(args, &mut description)?;
+        (unit, args, env)
+      }
+      Step::Run(_, env) => {
+        description.push_str("run compiled code");
+        (unit, unit, env)
+      }
+      Step::Return(value) => {
+        description.push_str("return ");
+        self.show(value, &mut description)?;
+        (value, value, self.env)
+      }
+    };
+    self.pause_id += 1;
+    let pause = self.pause_id;
+    self.debugger = Some(Debugger { step: next, pause: pause, ..debugger });
+    return Ok(DebugStep::Step {
+      result: result,
+      next_value: next_value,
+      next_env: next_env,
+      description: description,
+      pause: pause,
+    });
+  }
+
+  /// Give every evaluation the host starts from now on a budget of
+  /// `n` steps; see `call_with_budget`.
+  pub fn set_step_limit(&mut self, n: usize) {
+    self.step_limit = Some(n);
+  }
+
+  /// Write `value` to `buf` in `mode`; see `ShowMode`.
+  pub fn show_mode(&self, pointer: Gc, mode: ShowMode, buf: &mut String) -> Result<()> {
+    let mut active = HashSet::new();
+    return self.show_in(pointer, mode, &mut active, buf);
+  }
+
+  /// Write `value` for `show_mode`, inside the pairs, vectors and hash
+  /// tables in `active`. One of those met again is a cycle, and shows
+  /// as `#<cycle>`; structure that's only shared shows in full.
+  fn show_in(&self, pointer: Gc, mode: ShowMode, active: &mut HashSet<Gc>, buf: &mut String) -> Result<()> {
+    let object = self.heap.get(pointer)?;
+    if let Object::Pair(_) | Object::Vector(_) | Object::HashMap(_) = object {
+      if !active.insert(pointer) {
+        buf.push_str("#<cycle>");
+        return Ok(());
+      }
+    }
+    match object {
+      Object::Unit => {
+        buf.push('#');
+      }
+      Object::Bool(value) => {
+        if value {
+          buf.push_str("#t");
+        } else {
+          buf.push_str("#f");
+        }
+      }
+      Object::Number(value) => {
+        buf.push_str(&value.to_string());
+      }
+      Object::Rational { numer, denom } => {
+        buf.push_str(&format!("{}/{}", numer, denom));
+      }
+      #[cfg(feature = "bignum")]
+      Object::BigInt(ref value) => {
+        buf.push_str(&value.to_string());
+      }
+      #[cfg(feature = "bignum")]
+      Object::BigRational { ref numer, ref denom } => {
+        buf.push_str(&format!("{}/{}", numer, denom));
+      }
+      Object::Symbol(ref value) => {
+        buf.push_str(&value.0);
+      }
+      Object::Pair(ref value) => {
+        if !value.is_list {
+          buf.push('(');
+          self.show_in(value.fst, mode, active, buf)?;
+          buf.push_str(" * ");
+          self.show_in(value.snd, mode, active, buf)?;
+          buf.push(')');
+        } else {
+          buf.push('(');
+          // The rest of the cells are active too, for anything inside
+          // that refers back to the middle of the list.
+          let mut cells = vec![];
+          let mut xs = pointer;
+          while let Object::Pair(ref value) = self.heap.get(xs)? {
+            self.show_in(value.fst, mode, active, buf)?;
+            if !self.heap.get(value.snd)?.is_unit() {
+              buf.push(' ');
+            }
+            xs = value.snd;
+            if active.insert(xs) {
+              cells.push(xs);
+            }
+          }
+          guard(self.heap.get(xs)?.is_unit())?;
+          buf.push(')');
+          for cell in cells.iter() {
+            active.remove(cell);
+          }
+        }
+      }
+      Object::Proc(_) => {
+        buf.push_str("<procedure>");
+      }
+      Object::HashMap(ref table) => {
+        buf.push_str("#hash(");
+        for (index, (key, value)) in sorted_entries(&table.borrow()).iter().enumerate() {
+          if index > 0 {
+            buf.push(' ');
+          }
+          buf.push('(');
+          match key.split_at(1) {
+            ("'", name) => buf.push_str(name),
+            (_, text) => match mode {
+              ShowMode::Write => show_string(text, buf),
+              ShowMode::Display => buf.push_str(text),
+            },
+          }
+          buf.push_str(" * ");
+          self.show_in(*value, mode, active, buf)?;
+          buf.push(')');
+        }
+        buf.push(')');
+      }
+      Object::Macro(_) => {
+        buf.push_str("<macro>");
+      }
+      Object::Env(_) => {
+        buf.push_str("<environment>");
+      }
+      Object::Vector(ref value) => {
+        buf.push_str("#(");
+        for (index, x) in value.borrow().iter().enumerate() {
+          if index > 0 {
+            buf.push(' ');
+          }
+          self.show_in(*x, mode, active, buf)?;
+        }
+        buf.push(')');
+      }
+      Object::Port(ref port) => {
+        buf.push_str("#<port \"");
+        buf.push_str(&port.borrow().name);
+        buf.push_str("\">");
+      }
+      Object::Eof => {
+        buf.push_str("#<eof>");
+      }
+      Object::Builder(_) => {
+        buf.push_str("#<string-builder>");
+      }
+      Object::Foreign(ref foreign) => {
+        match self.printers.get(&foreign.name) {
+          Some(printer) => printer(&*foreign.value, buf),
+          None => {
+            buf.push_str("#<foreign:");
+            buf.push_str(&foreign.name);
+            buf.push('>');
+          }
+        }
+      }
+      Object::MultipleValues(ref values) => {
+        for (index, x) in values.iter().enumerate() {
+          if index > 0 {
+            buf.push(' ');
+          }
+          self.show_in(*x, mode, active, buf)?;
+        }
+      }
+      Object::Bytevector(ref value) => {
+        buf.push_str("#u8(");
+        for (index, byte) in value.borrow().iter().enumerate() {
+          if index > 0 {
+            buf.push(' ');
+          }
+          buf.push_str(&byte.to_string());
+        }
+        buf.push(')');
+      }
+      Object::Str(ref value) => {
+        match mode {
+          ShowMode::Write => show_string(value, buf),
+          ShowMode::Display => buf.push_str(value),
+        }
+      }
+      Object::Char(rune) => {
+        match mode {
+          ShowMode::Write => show_char(rune, buf),
+          ShowMode::Display => buf.push(rune),
+        }
+      }
+    }
+    active.remove(&pointer);
+    return Ok(());
+  }
+
+  /// Write `value` for `pretty_show`, starting at `column`, with
+  /// `trail` closing brackets to follow it on the same line, inside the
+  /// lists and vectors in `active` that had to be broken up.
+  fn pretty(&self, value: Gc, width: usize, column: usize, trail: usize, active: &mut HashSet<Gc>, buf: &mut String) -> Result<()> {
+    let mut flat = String::new();
+    self.show(value, &mut flat)?;
+    if column + flat.chars().count() + trail <= width {
+      buf.push_str(&flat);
+      return Ok(());
+    }
+    // `None` is the `*` between the halves of an improper pair.
+    let (open, items) = match self.heap.get(value)? {
+      Object::Pair(ref pair) if pair.is_list => ("(", self.args(value)?.into_iter().map(Some).collect()),
+      Object::Pair(ref pair) => ("(", vec![Some(pair.fst), None, Some(pair.snd)]),
+      Object::Vector(ref xs) => ("#(", xs.borrow().iter().cloned().map(Some).collect()),
+      _ => {
+        buf.push_str(&flat);
+        return Ok(());
+      }
+    };
+    if !active.insert(value) {
+      buf.push_str("#<cycle>");
+      return Ok(());
+    }
+    let column = column + open.len();
+    let last = items.len().saturating_sub(1);
+    buf.push_str(open);
+    for (index, item) in items.into_iter().enumerate() {
+      if index > 0 {
+        buf.push('\n');
+        buf.extend(std::iter::repeat_n(' ', column));
+      }
+      match item {
+        Some(item) => {
+          let trail = if index == last { trail + 1 } else { 0 };
+          self.pretty(item, width, column, trail, active, buf)?;
+        }
+        None => buf.push('*'),
+      }
+    }
+    buf.push(')');
+    active.remove(&value);
+    return Ok(());
+  }
+
+  /// Wrap a host value as a foreign object with the type `name`, which
+  /// Lisp code can pass around and compare with `eq?` but not look into.
+  pub fn foreign<T: Any>(&mut self, name: &str, value: T) -> Result<Gc> {
+    let foreign = Foreign { name: Rc::from(name), value: Rc::new(value) };
+    return self.heap.put(Object::Foreign(foreign));
+  }
+
+  /// The host value in the foreign object `value`, or a type error if
+  /// it isn't one holding a `T`.
+  pub fn foreign_ref<T: Any>(&self, value: Gc) -> Result<Rc<T>> {
+    match self.heap.get(value)? {
+      Object::Foreign(foreign) => return foreign.value.downcast().map_err(|_| Error::Type),
+      _ => return Err(Error::Type),
+    }
+  }
+
+  /// Have `show` write foreign values of the type `name` with
+  /// `printer`, given the host value, in place of the default
+  /// `#<foreign:name>`. A later printer for the same type replaces it.
+  pub fn set_printer(&mut self, name: &str, printer: impl Fn(&dyn Any, &mut String) + 'static) {
+    self.printers.insert(Rc::from(name), Rc::new(printer));
+  }
+
+  /// Have `equal?` compare two foreign values of the type `name` by
+  /// calling `equality` on their host values, where otherwise they'd
+  /// only be equal if they were the same object. Values of different
+  /// types are never equal. A later equality for the same type
+  /// replaces it.
+  pub fn set_equality(&mut self, name: &str, equality: impl Fn(&dyn Any, &dyn Any) -> bool + 'static) {
+    self.equalities.insert(Rc::from(name), Rc::new(equality));
+  }
+
+  /// A new interpreter of its own, with a heap bounded at
+  /// `SANDBOX_CAPACITY` nodes, this one's step limit, and environments
+  /// without the `UNSAFE` built-ins, for running code that isn't
+  /// trusted, and no capabilities this one doesn't have. It collects
+  /// incrementally, so running out of room still leaves it usable; see
+  /// `set_incremental`. Values can't cross between the two, since they
+  /// live on different heaps, but source can.
+  pub fn sandbox(&self) -> V0 {
+    let mut lisp = V0::with_mode(SANDBOX_CAPACITY, Some(SANDBOX_CAPACITY), true, self.capabilities).unwrap();
+    lisp.step_limit = self.step_limit;
+    lisp.set_incremental(true);
+    return lisp;
+  }
+
+  /// Evaluate the forms in `src` in a new `sandbox`, in at most `steps`
+  /// steps, and copy the last one's value back here. All the built-ins
+  /// are there but the `UNSAFE` ones, so no IO or `eval`, and the heap
+  /// and the length of anything in it are bounded. The steps count
+  /// against this interpreter's own budget too. The value has to be
+  /// something `to_json` can write, and comes back as `read_json` reads
+  /// that; running out of steps or room is `Error::Time` or
+  /// `Error::Space`, like any other error inside.
+  pub fn sandbox_eval(&mut self, src: &str, steps: usize) -> Result<Gc> {
+    let steps = self.budget.map_or(steps, |budget| budget.min(steps));
+    let mut sandbox = self.sandbox();
+    sandbox.step_limit = Some(steps);
+    let mut forms = sandbox.read(src)?;
+    let sequence = sandbox.symbol(Rc::from("$sequence"))?;
+    forms.insert(0, sequence);
+    let body = sandbox.list(&forms)?;
+    sandbox.loading.push(body);
+    let env = sandbox.env;
+    let result = sandbox.call_with_budget(body, env, steps);
+    let used = steps - sandbox.budget.unwrap_or(0);
+    self.budget = self.budget.map(|budget| budget.saturating_sub(used));
+    let mut buf = String::new();
+    sandbox.to_json(result?, &mut buf)?;
+    return self.read_json(&buf);
+  }
+
+  /// Call `hook` with every expression just before it's evaluated, and
+  /// the interpreter as it is then, or stop calling one with `None`.
+  /// Code `compile` made runs without evaluating expressions, so the
+  /// hook doesn't see inside it.
+  pub fn set_trace_hook(&mut self, hook: Option<TraceHook>) {
+    self.trace_hook = hook;
+  }
+
+  pub fn clear_trace_hook(&mut self) {
+    self.trace_hook = None;
+  }
+
+  /// Write each call to a procedure given to `trace` to `writer`
+  /// instead of stdout.
+  pub fn set_trace_writer(&mut self, writer: Box<dyn Write>) {
+    self.trace_writer = writer;
+  }
+
+  pub fn global_env(&self) -> Gc {
+    return self.env;
+  }
+
+  /// Bind the symbol `name` to `value` in `env`'s own frame.
+  pub fn define(&mut self, env: Gc, name: Gc, value: Gc) -> Result<()> {
+    return define(env, name, value, self);
+  }
+
+  pub fn is_unit(&self, value: Gc) -> Result<bool> {
+    return Ok(self.heap.get(value)?.is_unit());
+  }
+
+  /// The (name, value) bindings in `env`'s own frame, newest first,
+  /// or by name if `sorted`.
+  pub fn bindings(&mut self, env: Gc, sorted: bool) -> Result<Vec<(Gc, Gc)>> {
+    let value = match self.heap.get(env)? {
+      Object::Env(value) => value,
+      _ => return Err(Error::Type),
+    };
+    let mut bindings = vec![];
+    for binding in self.list_iter(value.frame) {
+      match self.heap.get(binding?)? {
+        Object::Pair(binding) => bindings.push((binding.fst, binding.snd)),
+        _ => return Err(Error::Type),
+      }
+    }
+    if let Some(ref slots) = value.slots {
+      let values = slots.values.borrow().clone();
+      for (name, slot) in slots.names.iter().zip(values).rev() {
+        if let Some(slot) = slot {
+          let name = self.symbol(name.clone())?;
+          bindings.push((name, slot));
+        }
+      }
+    }
+    if sorted {
+      let mut keyed = vec![];
+      for (name, value) in bindings {
+        let key = match self.heap.get(name)? {
+          Object::Symbol(symbol) => symbol.0,
+          _ => return Err(Error::Type),
+        };
+        keyed.push((key, name, value));
+      }
+      keyed.sort_by(|a, b| a.0.cmp(&b.0));
+      bindings = keyed.into_iter().map(|(_, name, value)| (name, value)).collect();
+    }
+    return Ok(bindings);
+  }
+
+  pub fn set_lookup_cache(&mut self, on: bool) {
+    self.lookup_cache = if on { Some(HashMap::new()) } else { None };
+  }
+
+  /// Turn the call site cache `operator` keeps on or off. It's on by
+  /// default; off, every call looks its operator up afresh.
+  pub fn set_call_cache(&mut self, on: bool) {
+    self.call_cache = if on { Some(HashMap::new()) } else { None };
+  }
+
+  /// Drop everything the lookup and call site caches know, as after a
+  /// collection, which may have freed what they point at.
+  fn clear_caches(&mut self) {
+    if let Some(ref mut cache) = self.lookup_cache {
+      cache.clear();
+    }
+    if let Some(ref mut cache) = self.call_cache {
+      cache.clear();
+    }
+  }
+
+  /// Note that `name` has just gained or lost a binding somewhere.
+  fn forget(&mut self, name: &str) {
+    if let Some(ref mut cache) = self.lookup_cache {
+      cache.remove(name);
+    }
+    if !self.compile_vars.is_empty() && self.compile_vars.contains(name) {
+      self.compile_epoch += 1;
+    }
+    if let Some(version) = self.versions.get(name) {
+      version.set(version.get() + 1);
+    }
+  }
+
+  pub fn set_loose_brackets(&mut self, loose: bool) {
+    self.loose_brackets = loose;
+  }
+
+  /// Compile `value` against `env` into an applicative of no arguments
+  /// that evaluates it there, for code that's run over and over. It
+  /// stays correct as `env` changes: see `compile` for when it's
+  /// compiled again.
+  pub fn compile(&mut self, value: Gc, env: Gc) -> Result<Gc> {
+    match self.heap.get(env)? {
+      Object::Env(_) => {}
+      _ => return Err(Error::Type),
+    }
+    let code = compile(value, env, self)?;
+    let compiled = Compiled { datum: value, env: env, epoch: self.compile_epoch, code: code };
+    let proc = self.heap.put(Object::Proc(Proc::Compiled(compiled)))?;
+    return self.heap.put(Object::Proc(Proc::App(App(proc))));
+  }
+
+  /// Run something `compile` returned.
+  pub fn run_compiled(&mut self, compiled: Gc) -> Result<Gc> {
+    let args = self.unit()?;
+    let env = self.env;
+    return self.call(compiled, args, env);
+  }
+
+  /// Note that `name` has just been bound or unbound somewhere.
+  fn rebind(&mut self, name: &str) {
+    if !self.compile_names.is_empty() && self.compile_names.contains(name) {
+      self.compile_epoch += 1;
+    }
+  }
+
+  pub fn stats(&self) -> HeapStats {
+    return self.heap.stats();
+  }
+
+  /// Every node in the heap, a line each; see `Heap::dump`.
+  pub fn dump_heap(&self, buf: &mut String) -> Result<()> {
+    return self.heap.dump(buf, |pointer, buf| self.show(pointer, buf));
+  }
+
+  /// Pin `value` so collections keep it, and everything it reaches,
+  /// alive while the returned guard is.
+  pub fn root(&mut self, value: Gc) -> RootGuard {
+    let mut pinned = self.pinned.borrow_mut();
+    let slot = match pinned.iter().position(|slot| slot.is_none()) {
+      Some(slot) => slot,
+      None => {
+        pinned.push(None);
+        pinned.len() - 1
+      }
+    };
+    pinned[slot] = Some(value);
+    return RootGuard { slot: slot, pinned: self.pinned.clone() };
+  }
+
+  /// Collect, then move everything live to the front of the heap so
+  /// free nodes are contiguous. Roots, pinned values and the intern
+  /// tables are updated; any other `Gc` the host holds is left pointing
+  /// at the old index, where its timestamp no longer matches, so using
+  /// it is `Error::Pointer` rather than a wrong object.
+  pub fn compact(&mut self) {
+    self.gc();
+    let forward = self.heap.compact();
+    let f = |pointer: Gc| forward.get(&pointer).cloned().unwrap_or(pointer);
+    self.env = f(self.env);
+    self.list_proc = f(self.list_proc);
+    for pointer in self.small.iter_mut() {
+      *pointer = f(*pointer);
+    }
+    for weak in self.symbols.values_mut() {
+      let pointer = Gc { index: weak.index, timestamp: weak.timestamp };
+      *weak = f(pointer).downgrade();
+    }
+    for pointer in self.loading.iter_mut() {
+      *pointer = f(*pointer);
+    }
+    for slot in self.pinned.borrow_mut().iter_mut() {
+      *slot = slot.map(f);
+    }
+    for frame in self.stack.iter_mut() {
+      frame.rewrite(&f);
+    }
+    if let Some(ref mut escape) = self.escape {
+      escape.rewrite(&f);
+    }
+    self.winds = rewrite_winds(&self.winds, &f);
+    for binding in self.params.iter_mut() {
+      *binding = (f(binding.0), f(binding.1));
+    }
+    for alias in self.aliases.values_mut() {
+      alias.env = f(alias.env);
+    }
+    for module in self.modules.values_mut() {
+      *module = f(*module);
+    }
+    for value in self.stored.iter_mut() {
+      *value = f(*value);
+    }
+    if let Some(ref mut debugger) = self.debugger {
+      debugger.step.rewrite(&f);
+    }
+    for node in self.traces.values_mut().flatten() {
+      node.expr = f(node.expr);
+      node.result = node.result.map(&f);
+    }
+  }
+
+  /// Everything the collector has to keep alive: the global
+  /// environment, the small integer table, `list_proc`, values pinned
+  /// by the host, and whatever the machine is holding on its stack.
+  fn roots(&self, buf: &mut Vec<Gc>) {
+    buf.push(self.env);
+    buf.extend(self.pinned.borrow().iter().flatten());
+    buf.extend(self.loading.iter());
+    buf.extend(self.small.iter());
+    buf.push(self.list_proc);
+    for frame in self.stack.iter() {
+      frame.pointers(buf);
+    }
+    if let Some(ref escape) = self.escape {
+      escape.pointers(buf);
+    }
+    for wind in self.winds.iter() {
+      wind.pointers(buf);
+    }
+    for &(param, value) in self.params.iter() {
+      buf.push(param);
+      buf.push(value);
+    }
+    buf.extend(self.aliases.values().map(|alias| alias.env));
+    buf.extend(self.modules.values());
+    buf.extend(self.stored.iter());
+    if let Some(ref debugger) = self.debugger {
+      debugger.step.pointers(buf);
+    }
+    for node in self.traces.values().flatten() {
+      buf.push(node.expr);
+      buf.extend(node.result);
+    }
+  }
+
+  /// Make `value` immutable, and the pairs, vectors and bytevectors it's
+  /// made of, but not any procedure or environment in it. Mutators
+  /// given a frozen node change a copy instead; see `thaw`.
+  fn freeze(&mut self, value: Gc) -> Result<()> {
+    let mut todo = vec![value];
+    while let Some(pointer) = todo.pop() {
+      if self.heap.is_frozen(pointer) {
+        continue;
+      }
+      match self.heap.get(pointer)? {
+        Object::Pair(pair) => {
+          todo.push(pair.fst);
+          todo.push(pair.snd);
+        }
+        Object::Vector(values) => {
+          todo.extend(values.borrow().iter());
+        }
+        Object::Bytevector(_) => {}
+        _ => continue,
+      }
+      self.heap.frozen[pointer.index] = true;
+    }
+    return Ok(());
+  }
+
+  /// How many nodes can be reached from `value`, itself included, each
+  /// counted once however much it's shared.
+  fn value_size(&self, value: Gc) -> Result<usize> {
+    let mut seen = HashSet::new();
+    let mut todo = vec![value];
+    while let Some(pointer) = todo.pop() {
+      if seen.insert(pointer) {
+        self.heap.get(pointer)?.pointers(&mut todo);
+      }
+    }
+    return Ok(seen.len());
+  }
+
+  /// `value` if it can be changed, otherwise a mutable copy of it for
+  /// a mutator to change instead, which is what the mutator returns.
+  /// The copy is shallow: what it holds is still shared with the
+  /// original, and still frozen, so updating something nested means
+  /// storing each changed node back into its parent, copying the path
+  /// up to the root and nothing else.
+  fn thaw(&mut self, value: Gc) -> Result<Gc> {
+    if !self.heap.is_frozen(value) {
+      return Ok(value);
+    }
+    let copy = match self.heap.get(value)? {
+      Object::Pair(pair) => Object::Pair(pair),
+      Object::Vector(values) => Object::Vector(Rc::new(RefCell::new(values.borrow().clone()))),
+      Object::Bytevector(bytes) => Object::Bytevector(Rc::new(RefCell::new(bytes.borrow().clone()))),
+      _ => return Ok(value),
+    };
+    return self.heap.put(copy);
+  }
+
+  /// The symbol `name` becomes in one expansion of a macro defined in
+  /// `env`, which is the same every time within it.
+  fn rename(&mut self, name: &Rc<str>, env: Gc, renames: &mut HashMap<Rc<str>, Gc>) -> Result<Gc> {
+    if let Some(&symbol) = renames.get(name) {
+      return Ok(symbol);
+    }
+    let alias: Rc<str> = Rc::from(format!("{}#{}", name, self.alias_id).as_str());
+    self.alias_id += 1;
+    self.aliases.insert(alias.clone(), Alias { name: name.clone(), env: env });
+    let symbol = self.symbol(alias)?;
+    renames.insert(name.clone(), symbol);
+    return Ok(symbol);
+  }
+
+  /// A symbol no other symbol is `eq?` to, left out of `symbols` so
+  /// reading its name gives a different one.
+  fn gensym(&mut self) -> Result<Gc> {
+    let name: Rc<str> = Rc::from(format!("g{}", self.gensym_id).as_str());
+    self.gensym_id += 1;
+    return self.heap.put(Object::Symbol(Symbol(name)));
+  }
+
+  /// The name `name` was renamed from, following any aliases.
+  fn original(&self, name: &Rc<str>) -> Rc<str> {
+    let mut name = name;
+    while let Some(alias) = self.aliases.get(name) {
+      name = &alias.name;
+    }
+    return name.clone();
+  }
+
+  /// The elements of the list `value`, and whatever ends it: `()` for a
+  /// proper list.
+  fn spine(&self, value: Gc) -> Result<(Vec<Gc>, Gc)> {
+    let mut items = vec![];
+    let mut value = value;
+    while let Object::Pair(pair) = self.heap.get(value)? {
+      items.push(pair.fst);
+      value = pair.snd;
+    }
+    return Ok((items, value));
+  }
+
+  /// Enter `wind`, which `during` or a body is about to run inside.
+  fn wind(&mut self, wind: Wind) {
+    self.params.extend(wind.bindings.iter().cloned());
+    self.winds.push(wind);
+  }
+
+  /// Leave the innermost wind.
+  fn unwind(&mut self) -> Option<Wind> {
+    let wind = self.winds.pop()?;
+    let len = self.params.len() - wind.bindings.len();
+    self.params.truncate(len);
+    return Some(wind);
+  }
+
+  fn env_new(&mut self, parent: Option<Gc>) -> Result<Gc> {
+    let frame = self.unit()?;
+    let env = Env { frame: frame, parent: parent, slots: None };
+    return self.heap.put(Object::Env(env));
+  }
+
+  fn number(&mut self, value: i64) -> Result<Gc> {
+    if (SMALL_MIN..=SMALL_MAX).contains(&value) {
+      self.small_hits += 1;
+      return Ok(self.small[(value - SMALL_MIN) as usize]);
+    }
+    self.small_misses += 1;
+    return self.heap.put(Object::Number(value));
+  }
+
+  /// `numer/denom`, reduced, which is a `Number` if it's whole.
+  fn rational(&mut self, numer: i64, denom: i64) -> Result<Gc> {
+    let (numer, denom) = reduce(numer as i128, denom as i128).ok_or(Error::Type)?;
+    if denom == 1 {
+      return self.number(numer);
+    }
+    return self.heap.put(Object::Rational { numer: numer, denom: denom });
+  }
+
+  /// The numerator and denominator of an integer or ratio.
+  fn fraction(&self, value: Gc) -> Result<(i64, i64)> {
+    match self.heap.get(value)? {
+      Object::Number(value) => {
+        return Ok((value, 1));
+      }
+      Object::Rational { numer, denom } => {
+        return Ok((numer, denom));
+      }
+      _ => {
+        return Err(Error::Type);
+      }
+    }
+  }
+
+  fn is_number(&self, value: Gc) -> Result<bool> {
+    match self.heap.get(value)? {
+      Object::Number(_) | Object::Rational { .. } => {
+        return Ok(true);
+      }
+      #[cfg(feature = "bignum")]
+      Object::BigInt(_) | Object::BigRational { .. } => {
+        return Ok(true);
+      }
+      _ => {
+        return Ok(false);
+      }
+    }
+  }
+
+  /// `fraction`, for numbers of any size.
+  #[cfg(feature = "bignum")]
+  fn big_fraction(&self, value: Gc) -> Result<(BigInt, BigInt)> {
+    match self.heap.get(value)? {
+      Object::BigInt(value) => {
+        return Ok(((*value).clone(), BigInt::one()));
+      }
+      Object::BigRational { numer, denom } => {
+        return Ok(((*numer).clone(), (*denom).clone()));
+      }
+      _ => {
+        let (numer, denom) = self.fraction(value)?;
+        return Ok((BigInt::from(numer), BigInt::from(denom)));
+      }
+    }
+  }
+
+  /// `rational`, for parts of any size. The result is only big if it
+  /// has to be.
+  #[cfg(feature = "bignum")]
+  fn big_rational(&mut self, numer: BigInt, denom: BigInt) -> Result<Gc> {
+    if denom.is_zero() {
+      return Err(Error::Type);
+    }
+    let gcd = numer.gcd(&denom);
+    let (mut numer, mut denom) = (numer / &gcd, denom / &gcd);
+    if denom.is_negative() {
+      numer = -numer;
+      denom = -denom;
+    }
+    if let (Some(numer), Some(denom)) = (numer.to_i64(), denom.to_i64()) {
+      return self.rational(numer, denom);
+    }
+    if denom.is_one() {
+      return self.heap.put(Object::BigInt(Rc::new(numer)));
+    }
+    return self.heap.put(Object::BigRational { numer: Rc::new(numer), denom: Rc::new(denom) });
+  }
+
+  /// The numerator and denominator of any number, as numbers.
+  #[cfg(feature = "bignum")]
+  fn parts(&mut self, value: Gc) -> Result<(Gc, Gc)> {
+    let (numer, denom) = self.big_fraction(value)?;
+    let numer = self.big_rational(numer, BigInt::one())?;
+    let denom = self.big_rational(denom, BigInt::one())?;
+    return Ok((numer, denom));
+  }
+
+  #[cfg(not(feature = "bignum"))]
+  fn parts(&mut self, value: Gc) -> Result<(Gc, Gc)> {
+    let (numer, denom) = self.fraction(value)?;
+    return Ok((self.number(numer)?, self.number(denom)?));
+  }
+
+  fn is_true(&self, value: Gc) -> Result<bool> {
+    match self.heap.get(value)? {
+      Object::Bool(flag) => {
+        return Ok(flag);
+      }
+      _ => {
+        return Ok(true);
+      }
+    }
+  }
+
+  fn integer(&self, value: Gc) -> Result<i64> {
+    match self.heap.get(value)? {
+      Object::Number(value) => {
+        return Ok(value);
+      }
+      _ => {
+        return Err(Error::Type);
+      }
+    }
+  }
+
+  /// Fail with `Error::Space` if this is a `sandbox` and `len` is past
+  /// `SANDBOX_LENGTH`.
+  fn fits(&self, len: usize) -> Result<()> {
+    if self.sandboxed && len > SANDBOX_LENGTH {
+      return Err(Error::Space);
+    }
+    return Ok(());
+  }
+
+  fn index(&self, value: Gc) -> Result<usize> {
+    let value = self.integer(value)?;
+    guard(value >= 0)?;
+    return Ok(value as usize);
+  }
+
+  fn vector(&self, value: Gc) -> Result<Rc<RefCell<Vec<Gc>>>> {
+    match self.heap.get(value)? {
+      Object::Vector(value) => {
+        return Ok(value);
+      }
+      _ => {
+        return Err(Error::Type);
+      }
+    }
+  }
+
+  fn string(&self, value: Gc) -> Result<Rc<str>> {
+    match self.heap.get(value)? {
+      Object::Str(value) => {
+        return Ok(value);
+      }
+      _ => {
+        return Err(Error::Type);
+      }
+    }
+  }
+
+  fn builder(&self, value: Gc) -> Result<Rc<RefCell<String>>> {
+    match self.heap.get(value)? {
+      Object::Builder(buf) => {
+        return Ok(buf);
+      }
+      _ => {
+        return Err(Error::Type);
+      }
+    }
+  }
+
+  fn hash_table(&self, value: Gc) -> Result<Rc<RefCell<HashMap<String, Gc>>>> {
+    match self.heap.get(value)? {
+      Object::HashMap(table) => {
+        return Ok(table);
+      }
+      _ => {
+        return Err(Error::Type);
+      }
+    }
+  }
+
+  /// The keys and values in the hash table `value`, in key order.
+  fn hash_entries(&mut self, value: Gc) -> Result<Vec<(Gc, Gc)>> {
+    let table = self.hash_table(value)?;
+    let mut result = vec![];
+    for (key, value) in sorted_entries(&table.borrow()) {
+      let key = match key.split_at(1) {
+        ("'", name) => self.symbol(Rc::from(name))?,
+        (_, text) => self.heap.put(Object::Str(Rc::from(text)))?,
+      };
+      result.push((key, value));
+    }
+    return Ok(result);
+  }
+
+  fn bytevector(&self, value: Gc) -> Result<Rc<RefCell<Vec<u8>>>> {
+    match self.heap.get(value)? {
+      Object::Bytevector(value) => {
+        return Ok(value);
+      }
+      _ => {
+        return Err(Error::Type);
+      }
+    }
+  }
+
+  /// The values `value` stands for, as a list: its elements if it came
+  /// from `values`, or else just itself.
+  fn unpack_values(&mut self, value: Gc) -> Result<Gc> {
+    match self.heap.get(value)? {
+      Object::MultipleValues(values) => {
+        return self.list(&values);
+      }
+      _ => {
+        let unit = self.unit()?;
+        return self.pair(value, unit);
+      }
+    }
+  }
+
+  fn port(&self, value: Gc) -> Result<Rc<RefCell<Port>>> {
+    match self.heap.get(value)? {
+      Object::Port(value) => {
+        return Ok(value);
+      }
+      _ => {
+        return Err(Error::Type);
+      }
+    }
+  }
+
+  /// The port given as `arg`, or else whatever `name` is bound to in
+  /// `env`, for the procedures whose port argument is optional.
+  fn port_or(&self, arg: Option<&Gc>, name: &str, env: Gc) -> Result<Rc<RefCell<Port>>> {
+    let port = match arg {
+      Some(&port) => port,
+      None => find(name, env, self)?.ok_or_else(|| Error::Unbound(Rc::from(name)))?,
+    };
+    return self.port(port);
+  }
+
+  fn byte(&self, value: Gc) -> Result<u8> {
+    let value = self.integer(value)?;
+    guard((0..=255).contains(&value))?;
+    return Ok(value as u8);
+  }
+
+  /// The `[start [end]]` arguments from `args[from]` on, checked
+  /// against a sequence of `len` items.
+  fn range(&self, args: &[Gc], from: usize, len: usize) -> Result<(usize, usize)> {
+    guard(args.len() <= from + 2)?;
+    let start = match args.get(from) {
+      Some(&start) => self.index(start)?,
+      None => 0,
+    };
+    let end = match args.get(from + 1) {
+      Some(&end) => self.index(end)?,
+      None => len,
+    };
+    guard(start <= end && end <= len)?;
+    return Ok((start, end));
+  }
+
+  fn args(&self, value: Gc) -> Result<Vec<Gc>> {
+    return self.list_iter(value).collect();
+  }
+
+  fn list(&mut self, values: &[Gc]) -> Result<Gc> {
+    let mut xs = self.unit()?;
+    for value in values.iter().rev() {
+      xs = self.pair(*value, xs)?;
+    }
+    return Ok(xs);
+  }
+
+  fn nth_tail(&self, value: Gc, count: usize) -> Result<Gc> {
+    let mut xs = value;
+    for _ in 0..count {
+      match self.heap.get(xs)? {
+        Object::Pair(ref pair) => { xs = pair.snd }
+        _ => { return Err(Error::Type) }
+      }
+    }
+    return Ok(xs);
+  }
+
+  fn call(&mut self, proc: Gc, args: Gc, env: Gc) -> Result<Gc> {
+    let proc = match self.heap.get(proc)? {
+      Object::Proc(Proc::App(ref app)) => app.0,
+      Object::Proc(_) => proc,
+      _ => return Err(Error::Type),
+    };
+    return run(Step::Apply(proc, args, env), self);
+  }
+}
+
+impl super::Lisp for V0 {
+  type Value = Gc;
+  type Error = Error;
+
+  fn unit(&mut self) -> Result<Self::Value> {
+    let object = Object::Unit;
+    return self.heap.put(object);
+  }
+
+  fn t(&mut self) -> Result<Self::Value> {
+    let object = Object::Bool(true);
+    return self.heap.put(object);
+  }
+
+  fn f(&mut self) -> Result<Self::Value> {
+    let object = Object::Bool(false);
+    return self.heap.put(object);
+  }
+
+  fn symbol(
+    &mut self,
+    value: Rc<str>) -> Result<Self::Value> {
+    if let Some(&weak) = self.symbols.get(&value) {
+      if let Some(pointer) = self.upgrade(weak) {
+        self.intern_hits += 1;
+        return Ok(pointer);
+      }
+    }
+    self.intern_misses += 1;
+    let symbol = Symbol(value.clone());
+    let object = Object::Symbol(symbol);
+    let pointer = self.heap.put(object)?;
+    self.symbols.insert(value, pointer.downgrade());
+    return Ok(pointer);
+  }
+
+  fn pair(
+    &mut self,
+    fst: Self::Value,
+    snd: Self::Value) -> Result<Self::Value> {
+    let is_list = match self.heap.get(snd)? {
+      Object::Unit            => true,
+      Object::Pair(ref value) => value.is_list,
+      _                       => false,
+    };
+    let pair = Pair { fst: fst, snd: snd, is_list: is_list };
+    let object = Object::Pair(pair);
+    return self.heap.put(object);
+  }
+
+  fn eval(
+    &mut self,
+    value: Self::Value,
+    env: Self::Value) -> Result<Self::Value> {
+    if self.runs.is_empty() {
+      self.budget = self.step_limit;
+    }
+    return run(Step::Eval(value, env), self);
+  }
+
+  fn read(
+    &mut self,
+    src: &str) -> Result<Vec<Self::Value>> {
+    return parse(Tokens::new(src.chars()), self);
+  }
+
+  fn load_string(
+    &mut self,
+    src: &str) -> Result<Vec<Self::Value>> {
+    if self.heap.is_full() {
+      self.gc();
+    }
+    let forms = self.read(src)?;
+    let base = self.loading.len();
+    self.loading.extend(&forms);
+    let mut values = Vec::with_capacity(forms.len());
+    for &form in forms.iter() {
+      // Between forms nothing is live but the forms and results, so
+      // this is where a full heap gets collected before `put` grows it.
+      if self.heap.is_full() {
+        self.gc();
+      }
+      let env = self.env;
+      match self.eval(form, env) {
+        Ok(value) => {
+          self.loading.push(value);
+          values.push(value);
+        }
+        Err(error) => {
+          self.loading.truncate(base);
+          return Err(error);
+        }
+      }
+    }
+    self.loading.truncate(base);
+    return Ok(values);
+  }
+
+  fn load_file(
+    &mut self,
+    path: &Path) -> Result<Vec<Self::Value>> {
+    let src = std::fs::read_to_string(path).map_err(|err| {
+      return Error::Io(Rc::from(format!("{}: {}", path.display(), err).as_str()));
+    })?;
+    return self.load_string(&src);
+  }
+
+  /// Lists and vectors that would run past `width` on one line have
+  /// their elements on lines of their own instead, each lined up under
+  /// the first, and the same goes for each element in turn. An atom is
+  /// never broken, even if it's too long by itself.
+  fn pretty_show(
+    &self,
+    pointer: Self::Value,
+    width: usize,
+    buf: &mut String) -> Result<()> {
+    let line = buf.rfind('\n').map_or(0, |index| index + 1);
+    let column = buf[line..].chars().count();
+    let mut active = HashSet::new();
+    return self.pretty(pointer, width, column, 0, &mut active, buf);
+  }
+
+  fn show(
+    &self,
+    pointer: Self::Value,
+    buf: &mut String) -> Result<()> {
+    return self.show_mode(pointer, ShowMode::Write, buf);
+  }
+
+  fn display(
+    &self,
+    pointer: Self::Value,
+    buf: &mut String) -> Result<()> {
+    return self.show_mode(pointer, ShowMode::Display, buf);
+  }
+
+  fn gc(&mut self) {
+    V0::gc(self);
+  }
+
+  fn list_iter<'a>(
+    &'a self,
+    value: Self::Value) -> impl Iterator<Item = Result<Self::Value>> + 'a {
+    return ListIter { heap: &self.heap, xs: Some(value) };
+  }
+}
+
+pub fn init(capacity: usize) -> V0 {
+  return V0::new(capacity).unwrap();
+}
+
+/// Like `init`, but the global environment, and any `(init)` makes,
+/// only has the built-ins `capabilities` allows.
+pub fn init_with(capacity: usize, capabilities: Capabilities) -> V0 {
+  return V0::with_mode(capacity, None, false, capabilities).unwrap();
+}
+
+/// Like `init`, but the heap never grows past `capacity` nodes. It's
+/// collected partway through an `eval` as it fills up, so a host has to
+/// `root` whatever it holds across one, and it's only `Error::Space`
+/// when what's live doesn't fit.
+pub fn init_bounded(capacity: usize) -> V0 {
+  return V0::with_mode(capacity, Some(capacity), false, Capabilities::ALL).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// What the last form of `src` evaluates to, shown.
+  fn run(lisp: &mut V0, src: &str) -> Result<String> {
+    let values = lisp.load_string(src)?;
+    let mut buf = String::new();
+    lisp.show(*values.last().ok_or(Error::Read)?, &mut buf)?;
+    return Ok(buf);
+  }
+
+  /// Like `run`, in a fresh interpreter, and it had better work.
+  fn eval(src: &str) -> String {
+    return run(&mut init(1024), src).unwrap();
+  }
+
+  #[test]
+  fn tree_map_keeps_the_shape() {
+    let src = "(tree-map ($lambda (x) (* 2 x)) ($quote (1 (2 3) (4 (5)))))";
+    assert_eq!(eval(src), "(2 (4 6) (8 (10)))");
+  }
+
+  #[test]
+  fn tree_map_handles_deep_trees() {
+    let mut lisp = init(1024);
+    let mut tree = lisp.number(7).unwrap();
+    for _ in 0..100_000 {
+      let unit = lisp.unit().unwrap();
+      tree = lisp.pair(tree, unit).unwrap();
+    }
+    let env = lisp.global_env();
+    let name = lisp.symbol(Rc::from("deep")).unwrap();
+    lisp.define(env, name, tree).unwrap();
+    assert_eq!(run(&mut lisp, "(pair? (tree-map ($lambda (x) (+ x 1)) deep))").unwrap(), "#t");
+  }
+
+  #[test]
+  fn tree_map_rejects_cycles() {
+    let mut lisp = init(1024);
+    let src = "($define! x (list 1 2)) (set-fst! x x) (tree-map ($lambda (y) y) x)";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Guard)));
+  }
+
+  #[test]
+  fn load_file_reports_the_io_error() {
+    let mut lisp = init(1024);
+    let path = Path::new("/nonexistent/softmacs/test.lisp");
+    match lisp.load_file(path) {
+      Err(Error::Io(message)) => {
+        assert!(message.starts_with("/nonexistent/softmacs/test.lisp: "), "{}", message);
+        assert!(message.len() > path.as_os_str().len() + 2);
+      }
+      other => panic!("expected an io error, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn load_file_evaluates_every_form() {
+    let path = std::env::temp_dir().join(format!("softmacs-load-{}.lisp", std::process::id()));
+    std::fs::write(&path, "($define! x 2) (+ x 3)").unwrap();
+    let mut lisp = init(1024);
+    let values = lisp.load_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(values.len(), 2);
+    assert_eq!(run(&mut lisp, "(+ x 3)").unwrap(), "5");
+  }
+
+  #[test]
+  fn small_integers_are_shared() {
+    assert_eq!(eval("(eq? (+ 1 1) 2)"), "#t");
+    assert_eq!(eval("(eq? (- 0 128) -128)"), "#t");
+    assert_eq!(eval("(eq? (+ 1000 1) 1001)"), "#f");
+  }
+
+  #[test]
+  fn intern_stats_count_symbols_and_numbers_apart() {
+    let mut lisp = init(1024);
+    let (hits, small) = (lisp.intern_hits, lisp.small_hits);
+    lisp.read("intern-me intern-me intern-me").unwrap();
+    assert_eq!(lisp.intern_hits, hits + 2);
+    assert_eq!(lisp.small_hits, small);
+    let misses = lisp.intern_misses;
+    lisp.number(3).unwrap();
+    lisp.number(1 << 20).unwrap();
+    assert_eq!(lisp.small_hits, small + 1);
+    assert_eq!(lisp.small_misses, 1);
+    assert_eq!(lisp.intern_misses, misses);
+    assert_eq!(run(&mut lisp, "(length (intern-stats))").unwrap(), "4");
+  }
+
+  #[test]
+  fn validate_heap_passes_a_healthy_heap() {
+    let src = "($define! xs (list 1 (list->vector (list 2 3)) \"four\")) (validate-heap)";
+    assert_eq!(eval(src), "#t");
+  }
+
+  /// Bind `name` to a pair made straight from `pair`, skipping the
+  /// checks `Lisp::pair` makes.
+  fn define_raw_pair(lisp: &mut V0, name: &str, pair: Pair) {
+    let pointer = lisp.heap.put(Object::Pair(pair)).unwrap();
+    let env = lisp.global_env();
+    let name = lisp.symbol(Rc::from(name)).unwrap();
+    lisp.define(env, name, pointer).unwrap();
+  }
+
+  #[test]
+  fn validate_heap_catches_a_dangling_pointer() {
+    let mut lisp = init(1024);
+    let unit = lisp.unit().unwrap();
+    let live = lisp.heap.put(Object::Str(Rc::from("x"))).unwrap();
+    let dead = Gc { index: live.index, timestamp: live.timestamp + 1_000_000 };
+    define_raw_pair(&mut lisp, "bad", Pair { fst: dead, snd: unit, is_list: true });
+    match run(&mut lisp, "(validate-heap)") {
+      Err(Error::Corrupt(message)) => assert!(message.contains("dead node"), "{}", message),
+      other => panic!("expected corruption, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn validate_heap_catches_a_wrong_is_list() {
+    let mut lisp = init(1024);
+    let one = lisp.number(1).unwrap();
+    define_raw_pair(&mut lisp, "bad", Pair { fst: one, snd: one, is_list: true });
+    match run(&mut lisp, "(validate-heap)") {
+      Err(Error::Corrupt(message)) => assert!(message.contains("is_list"), "{}", message),
+      other => panic!("expected corruption, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn call_cc_exits_a_deep_loop() {
+    let src = "(call/cc ($lambda (return)
+      (do ((i 0 (+ i 1))) ((= i 1000000) ($quote never))
+        ($if (= i 500) (return i) #f))))";
+    assert_eq!(eval(src), "500");
+  }
+
+  #[test]
+  fn call_cc_resumes_where_it_was_captured() {
+    let src = "(($lambda ()
+      ($sequence
+        ($define! count 0)
+        ($define! k (call/cc ($lambda (c) c)))
+        ($define! count (+ count 1))
+        ($if (< count 3) (k k) count))))";
+    assert_eq!(eval(src), "3");
+  }
+
+  #[test]
+  fn call_ec_exits_early() {
+    let src = "(call/ec ($lambda (break)
+      (do ((i 0 (+ i 1))) (#f) ($if (= i 7) (break (* i i)) #f))))";
+    assert_eq!(eval(src), "49");
+  }
+
+  #[test]
+  fn call_ec_fails_after_its_extent() {
+    let mut lisp = init(1024);
+    let src = "($define! k (call/ec ($lambda (c) c))) (k 1)";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Guard)));
+  }
+
+  #[test]
+  fn heap_time_running_out_is_a_space_error() {
+    let mut lisp = init(1024);
+    let kept = lisp.heap.put(Object::Str(Rc::from("kept"))).unwrap();
+    lisp.heap.time = usize::MAX - 1;
+    assert!(lisp.heap.put(Object::Unit).is_ok());
+    assert!(matches!(lisp.heap.put(Object::Unit), Err(Error::Space)));
+    assert!(matches!(lisp.heap.get(kept), Ok(Object::Str(_))));
+  }
+
+  #[test]
+  fn proper_tail_reads_is_list() {
+    assert_eq!(eval("(proper-tail? (pair 1 ()))"), "#t");
+    assert_eq!(eval("(proper-tail? (pair 1 2))"), "#f");
+    assert_eq!(eval("(proper-tail? (list 1 2 3))"), "#t");
+  }
+
+  #[test]
+  fn type_predicates() {
+    let cases = [
+      ("null?", "()", "1"),
+      ("pair?", "(pair 1 2)", "()"),
+      ("symbol?", "($quote a)", "\"a\""),
+      ("boolean?", "#f", "()"),
+      ("number?", "3/4", "($quote a)"),
+      ("string?", "\"a\"", "#\\a"),
+      ("char?", "#\\a", "\"a\""),
+      ("procedure?", "car", "($quote car)"),
+      ("vector?", "(make-vector 2 0)", "(list 1 2)"),
+    ];
+    for &(predicate, yes, no) in cases.iter() {
+      assert_eq!(eval(&format!("({} {})", predicate, yes)), "#t", "{} {}", predicate, yes);
+      assert_eq!(eval(&format!("({} {})", predicate, no)), "#f", "{} {}", predicate, no);
+    }
+  }
+
+  #[test]
+  fn chars_read_and_show() {
+    assert_eq!(eval("#\\a"), "#\\a");
+    assert_eq!(eval("#\\space"), "#\\space");
+    assert_eq!(eval("#\\("), "#\\(");
+    assert_eq!(eval("(list #\\x #\\newline)"), "(#\\x #\\newline)");
+    assert_eq!(eval("(eqv? #\\a #\\a)"), "#t");
+    assert_eq!(eval("(equal? #\\a #\\b)"), "#f");
+    let mut lisp = init(1024);
+    let rune = lisp.read("#\\z").unwrap()[0];
+    let mut buf = String::new();
+    lisp.display(rune, &mut buf).unwrap();
+    assert_eq!(buf, "z");
+    assert!(matches!(lisp.read("#\\bogus"), Err(Error::ReadAt { .. })));
+  }
+
+  #[test]
+  fn make_operative_builds_a_working_operative() {
+    let src = "
+      ($define! here (($vau () e e)))
+      ($define! quote-it (make-operative ($quote (x)) ($quote e) ($quote (x)) here))
+      (quote-it (+ 1 2))";
+    assert_eq!(eval(src), "(+ 1 2)");
+    let src = "
+      ($define! here (($vau () e e)))
+      ((make-applicative ($quote (x y)) ($quote ((+ x y))) here) 2 3)";
+    assert_eq!(eval(src), "5");
+    let mut lisp = init(1024);
+    assert!(run(&mut lisp, "(make-applicative ($quote (x)) ($quote (x)) 1)").is_err());
+  }
+
+  #[test]
+  fn procedure_source_round_trips_through_show() {
+    let mut lisp = init(1024);
+    let source = run(&mut lisp, "($define! f ($vau (x) e (pair x e))) (procedure-source f)").unwrap();
+    assert_eq!(source, "($vau (x) e (pair x e))");
+    let again = format!("($define! g {}) (fst (g (+ 1 2)))", source);
+    assert_eq!(run(&mut lisp, &again).unwrap(), "(+ 1 2)");
+    assert_eq!(run(&mut lisp, "(procedure-source car)").unwrap(), "#f");
+  }
+
+  #[test]
+  fn to_json_writes_a_mixed_structure() {
+    let mut lisp = init(1024);
+    let src = "(list 1 \"s\" ($quote sym) #t () (pair 1 2) #\\c)";
+    let value = *lisp.load_string(src).unwrap().last().unwrap();
+    let mut buf = String::new();
+    lisp.to_json(value, &mut buf).unwrap();
+    let expected = r#"[1,"s",{"symbol":"sym"},true,null,{"pair":[1,2]},{"char":"c"}]"#;
+    assert_eq!(buf, expected);
+    let src = "(value->json (list 1 (list->vector (list 2))))";
+    assert_eq!(run(&mut lisp, src).unwrap(), r#""[1,{\"vector\":[2]}]""#);
+  }
+
+  #[test]
+  fn to_json_rejects_cycles_and_procedures() {
+    let mut lisp = init(1024);
+    assert!(matches!(run(&mut lisp, "(value->json car)"), Err(Error::Type)));
+    let src = "($define! x (list 1 2)) (set-fst! x x) (value->json x)";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Guard)));
+  }
+
+  #[test]
+  fn equal_compares_vectors_by_content() {
+    assert_eq!(eval("(equal? (list->vector (list 1 (list 2))) (list->vector (list 1 (list 2))))"), "#t");
+    assert_eq!(eval("(equal? (list->vector (list 1 2)) (list->vector (list 1 3)))"), "#f");
+    assert_eq!(eval("(equal? (list->vector (list 1)) (list->vector (list 1 1)))"), "#f");
+    assert_eq!(eval("(eqv? (list->vector (list 1)) (list->vector (list 1)))"), "#f");
+  }
+
+  #[test]
+  fn read_json_builds_lists_and_alists() {
+    let mut lisp = init(1024);
+    let value = lisp.read_json(r#"[1, {"a": [2, 3]}, "s", null, true, {"symbol": "x"}]"#).unwrap();
+    let mut buf = String::new();
+    lisp.show(value, &mut buf).unwrap();
+    assert_eq!(buf, r#"(1 (("a" 2 3)) "s" # #t x)"#);
+    assert!(matches!(lisp.read_json("[1, 2"), Err(Error::Read)));
+    assert!(matches!(lisp.read_json("{\"a\" 1}"), Err(Error::Read)));
+  }
+
+  #[test]
+  fn json_round_trips() {
+    let mut lisp = init(1024);
+    let src = "($define! x (list 1 \"two\" ($quote three) (pair 4 5) (list->vector (list #\\6))))
+      (equal? x (json->value (value->json x)))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "#t");
+  }
+
+  #[test]
+  fn diff_pinpoints_a_deep_difference() {
+    let mut lisp = init(1024);
+    let values = lisp.read("(a (b c (d e f)) g) (a (b c (d x f)) g)").unwrap();
+    let text = diff(values[0], values[1], &lisp).unwrap().unwrap();
+    assert!(text.contains("1.2.1"), "{}", text);
+    assert!(text.contains('e') && text.contains('x'), "{}", text);
+    assert!(diff(values[0], values[0], &lisp).unwrap().is_none());
+    let src = "(diff ($quote (1 2 3)) ($quote (1 2)))";
+    assert!(run(&mut lisp, src).unwrap().contains("2.."));
+  }
+
+  #[test]
+  fn bounded_heap_collects_instead_of_running_out() {
+    let mut lisp = init_bounded(8192);
+    let src = "($define! loop ($lambda (n) ($if (= n 0) #t ($sequence (list 1 2 3 4 5 6 7 8) (loop (- n 1))))))
+      (loop 5000)";
+    assert_eq!(run(&mut lisp, src).unwrap(), "#t");
+    assert_eq!(lisp.stats().capacity, 8192);
+    let src = "($define! build ($lambda (n xs) ($if (= n 0) xs (build (- n 1) (pair n xs)))))
+      (build 10000 ())";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Space)));
+  }
+
+  #[test]
+  fn square_brackets_read_as_lists() {
+    assert_eq!(eval("($quote [1 2 3])"), "(1 2 3)");
+    assert_eq!(eval("($quote [a (b [c])])"), "(a (b (c)))");
+    assert_eq!(eval("(equal? ($quote [1 2]) (list 1 2))"), "#t");
+  }
+
+  #[test]
+  fn mismatched_brackets_are_an_error_unless_loose() {
+    let mut lisp = init(1024);
+    match lisp.read("(a\n b]") {
+      Err(Error::Mismatch { open, close }) => {
+        assert_eq!((open.line, close.line), (1, 2));
+        assert!(close.col > open.col);
+      }
+      other => panic!("expected a mismatch, got {:?}", other),
+    }
+    assert!(matches!(lisp.read("[a b)"), Err(Error::Mismatch { .. })));
+    lisp.set_loose_brackets(true);
+    assert_eq!(run(&mut lisp, "($quote (a b])").unwrap(), "(a b)");
+  }
+
+  #[test]
+  fn read_file_returns_the_forms_unevaluated() {
+    let path = std::env::temp_dir().join(format!("softmacs-read-{}.lisp", std::process::id()));
+    std::fs::write(&path, "(+ 1 2) ($define! y 7)").unwrap();
+    let mut lisp = init(1024);
+    let src = format!("($define! forms (read-file {:?})) forms", path.display().to_string());
+    let forms = run(&mut lisp, &src);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(forms.unwrap(), "((+ 1 2) ($define! y 7))");
+    assert!(matches!(run(&mut lisp, "y"), Err(Error::Unbound(_))));
+    assert_eq!(run(&mut lisp, "(eval (pair ($quote $sequence) forms) (($vau () e e))) y").unwrap(), "7");
+    assert!(matches!(run(&mut lisp, "(read-file \"/nonexistent/softmacs.lisp\")"), Err(Error::Io(_))));
+  }
+
+  #[test]
+  fn gc_step_collects_a_bit_at_a_time() {
+    let mut lisp = init(1024);
+    run(&mut lisp, "($define! xs (vector->list (make-vector 500 0))) ($define! xs 0)").unwrap();
+    let live = lisp.stats().live;
+    let mut steps = 1;
+    while !lisp.gc_step(100) {
+      steps += 1;
+    }
+    assert!(steps > 1);
+    assert!(lisp.stats().live + 500 <= live);
+    assert_eq!(run(&mut lisp, "xs").unwrap(), "0");
+  }
+
+  #[test]
+  fn validate_heap_ignores_garbage_mid_sweep() {
+    let mut lisp = init(1024);
+    run(&mut lisp, "($define! xs (vector->list (make-vector 500 0))) ($define! xs 0)").unwrap();
+    let mut sweeping = false;
+    for _ in 0..120 {
+      lisp.gc_step(100);
+      sweeping |= matches!(lisp.heap.phase, GcPhase::Sweeping(_));
+      assert_eq!(run(&mut lisp, "(validate-heap)").unwrap(), "#t");
+    }
+    assert!(sweeping);
+  }
+
+  #[test]
+  fn write_file_round_trips_through_read_file() {
+    let path = std::env::temp_dir().join(format!("softmacs-write-{}.lisp", std::process::id()));
+    let mut lisp = init(1024);
+    let src = format!(
+      "($define! path {:?}) ($define! x (list 1 \"two\" ($quote (three #\\4))))
+       (write-file path x) (equal? (fst (read-file path)) x)",
+      path.display().to_string());
+    let result = run(&mut lisp, &src);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(result.unwrap(), "#t");
+    assert!(matches!(run(&mut lisp, "(write-file \"/nonexistent/softmacs.lisp\" 1)"), Err(Error::Io(_))));
+  }
+
+  #[test]
+  fn opening_a_missing_file_is_an_io_error() {
+    match run(&mut init(1024), "(open-input-file \"/nonexistent/softmacs.txt\")").unwrap_err() {
+      Error::Io(message) => assert!(message.contains("No such file"), "{}", message),
+      other => panic!("expected an io error, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn environment_chain_runs_from_the_frame_outward() {
+    let mut lisp = init(1024);
+    let src = "($define! outer (($vau () e e)))
+      ($define! depth (length (environment-chain)))
+      (($lambda (x)
+         ($define! chain (environment-chain))
+         (list (= (length chain) (+ depth 1))
+               (eq? (fst chain) (($vau () e e)))
+               (eq? (fst (snd chain)) outer)))
+       1)";
+    assert_eq!(run(&mut lisp, src).unwrap(), "(#t #t #t)");
+  }
+
+  #[test]
+  fn bound_checks_without_raising() {
+    let mut lisp = init(1024);
+    let src = "($define! x 1) (list (bound? ($quote x)) (bound? ($quote no-such-name)) (bound? ($quote pair)))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "(#t #f #t)");
+    assert_eq!(run(&mut lisp, "(($lambda (y) (bound? ($quote y))) 1)").unwrap(), "#t");
+  }
+
+  #[test]
+  fn unbind_only_touches_the_frame_given() {
+    let mut lisp = init(1024);
+    let src = "($define! x 1)
+      (($lambda (x)
+         ($define! env (($vau () e e)))
+         (list (unbind! ($quote x) env) x (unbind! ($quote x) env)))
+       2)";
+    assert_eq!(run(&mut lisp, src).unwrap(), "(#t 1 #f)");
+    assert_eq!(run(&mut lisp, "(unbind! ($quote x)) (bound? ($quote x))").unwrap(), "#f");
+    assert!(matches!(run(&mut lisp, "x"), Err(Error::Unbound(_))));
+  }
+
+  #[test]
+  fn lookup_count_counts_each_lookup() {
+    let mut lisp = init(1024);
+    let src = "($define! x 1)
+      ($define! loop ($lambda (n) ($if (= n 0) #t ($sequence x (loop (- n 1))))))
+      (lookup-count ($quote x))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "#f");
+    assert_eq!(run(&mut lisp, "(count-lookups! ($quote x)) (loop 10) (lookup-count ($quote x))").unwrap(), "10");
+    assert_eq!(run(&mut lisp, "(loop 5) (lookup-count ($quote x))").unwrap(), "15");
+  }
+
+  #[test]
+  fn compile_resolves_variables_to_addresses() {
+    let mut lisp = init(1024);
+    let src = "($define! x 1) ($define! inner (($lambda (y) (($lambda (z) (($vau () e e))) 3)) 2))";
+    run(&mut lisp, src).unwrap();
+    let forms = lisp.read("(+ x y z no-such-name)").unwrap();
+    let inner = find("inner", lisp.env, &lisp).unwrap().unwrap();
+    let code = compile(forms[0], inner, &mut lisp).unwrap();
+    match &*code {
+      Code::Call(_, _, args) => {
+        assert!(matches!(&*args[0], &Code::Cell(..)));
+        assert!(matches!(&*args[1], &Code::Local(_, 1, 0)));
+        assert!(matches!(&*args[2], &Code::Local(_, 0, 0)));
+        assert!(matches!(&*args[3], Code::Var(_)));
+      }
+      _ => panic!("expected a call"),
+    }
+  }
+
+  #[test]
+  fn compiled_addresses_follow_new_bindings() {
+    let mut lisp = init(1024);
+    let src = "($define! x 1)
+      ($define! inner (($lambda (y) (($vau () e e))) 2))
+      ($define! sum (compile ($quote (+ x y)) inner))
+      (sum)";
+    assert_eq!(run(&mut lisp, src).unwrap(), "3");
+    assert_eq!(run(&mut lisp, "($define! x 10) (sum)").unwrap(), "12");
+    assert_eq!(run(&mut lisp, "(eval ($quote ($define! x 100)) inner) (sum)").unwrap(), "102");
+    assert_eq!(run(&mut lisp, "(unbind! ($quote y) inner) ($define! y 5) (sum)").unwrap(), "105");
+    let src = "(count-lookups! ($quote x)) (sum) (sum) (lookup-count ($quote x))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "2");
+  }
+
+  #[test]
+  fn value_size_counts_shared_nodes_once() {
+    let mut lisp = init(1024);
+    // Three pairs and the list's own `()` on top of the shared `xs`.
+    let src = "($define! xs (list 1000 2000 3000))
+      ($define! n (value-size xs))
+      (list (= (value-size (pair xs xs)) (+ n 1))
+            (= (value-size (list xs xs xs)) (+ n 4))
+            (= (value-size 12345) 1))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "(#t #t #t)");
+    let src = "($define! c (list 1000)) (set-fst! c c) (value-size c)";
+    assert_eq!(run(&mut lisp, src).unwrap(), "2");
+  }
+
+  #[test]
+  fn ratio_literals_read_in_lowest_terms() {
+    assert_eq!(eval("(list 6/8 -2/4 4/2 (/ 1 3))"), "(3/4 -1/2 2 1/3)");
+    let mut lisp = init(1024);
+    match lisp.read("(a 1/0)") {
+      Err(Error::ReadAt { message, .. }) => assert_eq!(&*message, "zero denominator"),
+      other => panic!("expected a read error, got {:?}", other.map(|_| ())),
+    }
+  }
+
+  #[test]
+  fn reading_a_ratio_into_a_full_heap_is_a_space_error() {
+    let mut lisp = init_bounded(8192);
+    while lisp.heap.put(Object::Unit).is_ok() {}
+    assert!(matches!(lisp.read("3/4"), Err(Error::Space)));
+  }
+
+  #[test]
+  fn bytevectors_round_trip_through_show() {
+    let mut lisp = init(1024);
+    let src = "($define! b (bytevector 1 2 255)) (bytevector-u8-set! b 0 7) b";
+    assert_eq!(run(&mut lisp, src).unwrap(), "#u8(7 2 255)");
+    assert_eq!(run(&mut lisp, "(equal? ($quote #u8(7 2 255)) b)").unwrap(), "#t");
+    assert_eq!(run(&mut lisp, "(list (bytevector-length b) (bytevector-u8-ref b 2))").unwrap(), "(3 255)");
+    assert!(matches!(run(&mut lisp, "(bytevector-u8-ref b 3)"), Err(Error::Guard)));
+    assert!(matches!(run(&mut lisp, "(bytevector-u8-set! b 0 256)"), Err(Error::Guard)));
+    assert!(matches!(run(&mut lisp, "(bytevector 1 -1)"), Err(Error::Guard)));
+  }
+
+  #[test]
+  fn utf8_round_trips_a_multibyte_string() {
+    let mut lisp = init(1024);
+    let src = "($define! b (string->utf8 \"héllo→\")) (bytevector-length b)";
+    assert_eq!(run(&mut lisp, src).unwrap(), "9");
+    assert_eq!(run(&mut lisp, "(utf8->string b)").unwrap(), "\"héllo→\"");
+    assert!(matches!(run(&mut lisp, "(utf8->string (bytevector 104 255))"), Err(Error::Read)));
+  }
+
+  #[test]
+  fn base64_round_trips_and_rejects_bad_input() {
+    let mut lisp = init(1024);
+    assert_eq!(run(&mut lisp, "(base64-encode (string->utf8 \"hello\"))").unwrap(), "\"aGVsbG8=\"");
+    let src = "($define! b (bytevector 0 1 2 253 254 255 7)) (equal? (base64-decode (base64-encode b)) b)";
+    assert_eq!(run(&mut lisp, src).unwrap(), "#t");
+    assert!(matches!(run(&mut lisp, "(base64-decode \"aGVsbG8\")"), Err(Error::Read)));
+    assert!(matches!(run(&mut lisp, "(base64-decode \"aGV*bG8=\")"), Err(Error::Read)));
+  }
+
+  #[test]
+  fn string_ref_returns_a_char() {
+    let mut lisp = init(1024);
+    let src = "($define! c (string-ref \"héllo\" 1)) (list c (char? c) (string? c))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "(#\\é #t #f)");
+    let forms = lisp.read("c").unwrap();
+    let env = lisp.env;
+    let c = lisp.eval(forms[0], env).unwrap();
+    let mut buf = String::new();
+    lisp.display(c, &mut buf).unwrap();
+    assert_eq!(buf, "é");
+    assert!(matches!(run(&mut lisp, "(string-ref \"abc\" 3)"), Err(Error::Guard)));
+  }
+
+  #[test]
+  fn ports_read_and_write_chars() {
+    let path = std::env::temp_dir().join(format!("softmacs-chars-{}.txt", std::process::id()));
+    let mut lisp = init(1024);
+    let src = format!(
+      "($define! path {:?})
+       ($define! out (open-output-file path))
+       (write-char (string-ref \"xé\" 1) out) (write-string \"z\" out) (close-port out)
+       ($define! in (open-input-file path))
+       (list (peek-char in) (read-char in) (read-char in) (eof-object? (read-char in)))",
+      path.display().to_string());
+    let result = run(&mut lisp, &src);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(result.unwrap(), "(#\\é #\\é #\\z #t)");
+    assert!(matches!(run(&mut lisp, "(write-char \"a\" out)"), Err(Error::Type)));
+  }
+
+  #[test]
+  fn hash_bytes_is_fnv1a() {
+    let mut lisp = init(1024);
+    let src = "(list (= (hash-bytes (bytevector 1 2 3)) (hash-bytes (bytevector 1 2 3)))
+                     (= (hash-bytes (bytevector 1 2 3)) (hash-bytes (bytevector 1 2 4)))
+                     (= (hash-bytes (bytevector)) (hash-bytes (bytevector 0))))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "(#t #f #f)");
+    assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+    assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+  }
+
+  #[test]
+  fn do_loops_until_its_test() {
+    assert_eq!(eval("(do ((i 0 (+ i 1)) (s 0 (+ s i))) ((= i 5) s))"), "10");
+    assert_eq!(eval("(do ((i 0 (+ i 1)) (xs () (pair i xs))) ((= i 3) 0 xs))"), "(2 1 0)");
+    assert_eq!(eval("(do ((i 0 (+ i 1))) ((= i 100000) i))"), "100000");
+  }
+
+  #[test]
+  fn store_gives_equal_values_one_id() {
+    let mut lisp = init(1024);
+    let src = "($define! a (freeze! (list 1 (freeze! (list 2 3)))))
+      ($define! b (freeze! (list 1 (freeze! (list 2 3)))))
+      ($define! c (freeze! (list 1 2)))
+      (list (= (store-put! a) (store-put! b)) (= (store-put! a) (store-put! c))
+            (equal? (store-ref (store-put! b)) a))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "(#t #f #t)");
+    assert!(run(&mut lisp, "(store-put! (list 1 2))").is_err());
+  }
+
+  #[test]
+  fn canonicalize_makes_equal_trees_eq() {
+    let mut lisp = init(1024);
+    let src = "($define! a (canonicalize (list 1 (list 2 3) \"s\")))
+      ($define! b (canonicalize (list 1 (list 2 3) \"s\")))
+      (list (eq? a b) (eq? (fst (snd a)) (fst (snd b))) (eq? a (canonicalize (list 1 2))))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "(#t #t #f)");
+  }
+
+  #[test]
+  fn tokens_are_pulled_one_at_a_time() {
+    let src = "(a b) ".repeat(10_000);
+    let pulled = Cell::new(0);
+    let mut tokens = Tokens::new(src.chars().inspect(|_| pulled.set(pulled.get() + 1)));
+    assert!(matches!(tokens.next(), Some(Ok(Token::Lparen(_)))));
+    assert!(pulled.get() <= 2, "{}", pulled.get());
+    assert!(matches!(tokens.next(), Some(Ok(Token::Symbol(..)))));
+    assert!(pulled.get() < 10);
+    let lazy = Tokens::new(src.chars()).count();
+    assert_eq!(tokenize(&src).unwrap().len(), lazy);
+  }
+
+  #[test]
+  fn parse_stops_pulling_at_an_error() {
+    let src = format!("(a b) ) {}", "(c d) ".repeat(10_000));
+    let pulled = Cell::new(0);
+    let tokens = Tokens::new(src.chars()).inspect(|_| pulled.set(pulled.get() + 1));
+    let mut lisp = init(1024);
+    assert!(parse(tokens, &mut lisp).is_err());
+    assert!(pulled.get() < 20, "{}", pulled.get());
+    let values = lisp.read(&"(c d) ".repeat(10_000)).unwrap();
+    assert_eq!(values.len(), 10_000);
+  }
+
+  #[test]
+  fn printers_show_foreign_values() {
+    let mut lisp = init(1024);
+    let point = lisp.foreign("point", (1, 2)).unwrap();
+    let other = lisp.foreign("other", 3).unwrap();
+    let mut buf = String::new();
+    lisp.show(point, &mut buf).unwrap();
+    assert_eq!(buf, "#<foreign:point>");
+    lisp.set_printer("point", |value, buf| {
+      let &(x, y) = value.downcast_ref::<(i32, i32)>().unwrap();
+      buf.push_str(&format!("#<point {} {}>", x, y));
+    });
+    buf.clear();
+    lisp.show(point, &mut buf).unwrap();
+    buf.push(' ');
+    lisp.show(other, &mut buf).unwrap();
+    assert_eq!(buf, "#<point 1 2> #<foreign:other>");
+  }
+
+  #[test]
+  fn equalities_compare_foreign_values() {
+    let mut lisp = init(1024);
+    let a = lisp.foreign("point", (1, 2)).unwrap();
+    let b = lisp.foreign("point", (1, 2)).unwrap();
+    let c = lisp.foreign("point", (1, 3)).unwrap();
+    assert!(!is_equal(a, b, &lisp).unwrap());
+    assert!(is_equal(a, a, &lisp).unwrap());
+    lisp.set_equality("point", |x, y| {
+      return x.downcast_ref::<(i32, i32)>() == y.downcast_ref::<(i32, i32)>();
+    });
+    assert!(is_equal(a, b, &lisp).unwrap());
+    assert!(!is_equal(a, c, &lisp).unwrap());
+    let xs = lisp.list(&[a, c]).unwrap();
+    let ys = lisp.list(&[b, c]).unwrap();
+    assert!(is_equal(xs, ys, &lisp).unwrap());
+  }
+
+  #[test]
+  fn string_builder_matches_string_append() {
+    let mut lisp = init(1024);
+    let src = "($define! sb (sb-new))
+      ($define! loop ($lambda (i s)
+        ($if (= i 500) s
+          ($sequence (sb-append! sb (number->string i) \",\")
+                     (loop (+ i 1) (string-append s (number->string i) \",\"))))))
+      ($define! s (loop 0 \"\"))
+      (list (string=? (sb-build sb) s) (string-length s))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "(#t 1890)");
+  }
+
+  #[test]
+  fn sandbox_eval_has_no_file_access() {
+    let mut lisp = init(1024);
+    assert_eq!(run(&mut lisp, "(sandbox-eval \"(+ 1 2)\" 1000)").unwrap(), "3");
+    let src = "(sandbox-eval \"(read-file \\\"/etc/passwd\\\")\" 1000)";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Unbound(_))));
+    let src = "(sandbox-eval \"($define! f ($lambda () (f))) (f)\" 1000)";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Time)));
+    for name in ["eval", "compile", "make-operative", "make-applicative", "init"] {
+      let src = format!("(sandbox-eval \"(bound? ($quote {}))\" 1000)", name);
+      assert_eq!(run(&mut lisp, &src).unwrap(), "#f", "{}", name);
+    }
+    let src = "(sandbox-eval \"((compile (list (string->symbol \\\"+\\\") 1 2) (init)))\" 1000)";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Unbound(_))));
+  }
+
+  #[test]
+  fn sandbox_eval_bounds_the_length_of_objects() {
+    let mut lisp = init(1024);
+    let src = "(sandbox-eval \"(bytevector-length (make-bytevector 1000000000 0))\" 10)";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Space)));
+    let src = "(sandbox-eval \"(vector-length (make-vector 50000000 0))\" 10)";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Space)));
+    let src = "(sandbox-eval \"(bytevector-length (make-bytevector 100 0))\" 10)";
+    assert_eq!(run(&mut lisp, src).unwrap(), "100");
+    assert_eq!(run(&mut lisp, "(bytevector-length (make-bytevector 100000 0))").unwrap(), "100000");
+  }
+
+  #[test]
+  fn capabilities_choose_the_built_ins() {
+    let only = Capabilities { arithmetic: true, ..Capabilities::NONE };
+    let mut lisp = init_with(1024, only);
+    assert_eq!(run(&mut lisp, "(+ 1 (* 2 3))").unwrap(), "7");
+    assert!(matches!(run(&mut lisp, "(list 1 2)"), Err(Error::Unbound(_))));
+    assert!(matches!(run(&mut lisp, "(string-length \"a\")"), Err(Error::Unbound(_))));
+    assert!(matches!(run(&mut lisp, "(read-file \"x\")"), Err(Error::Unbound(_))));
+    assert_eq!(run(&mut lisp, "($if #t 1 2)").unwrap(), "1");
+  }
+
+  #[test]
+  fn trace_eval_returns_the_evaluation_tree() {
+    let tree = eval("(trace-eval ($quote (+ 1 (* 2 3))))");
+    assert_eq!(tree, "((+ 1 (* 2 3)) 7 (1 1) ((* 2 3) 6 (2 2) (3 3)))");
+  }
+
+  /// A writer the test keeps a handle on after giving it away.
+  struct Shared(Rc<RefCell<Vec<u8>>>);
+
+  impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+      return self.0.borrow_mut().write(buf);
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+      return Ok(());
+    }
+  }
+
+  #[test]
+  fn traced_calls_go_to_the_trace_writer() {
+    let mut lisp = V0::new(1024).unwrap();
+    let out = Rc::new(RefCell::new(Vec::new()));
+    lisp.set_trace_writer(Box::new(Shared(out.clone())));
+    let src = "($define! f ($lambda (x y) (+ x y))) (trace f) (f 1 2)";
+    assert_eq!(run(&mut lisp, src).unwrap(), "3");
+    assert_eq!(String::from_utf8(out.borrow().clone()).unwrap(), "; (f 1 2)\n");
+  }
+
+  #[test]
+  fn make_vector_too_large_is_a_space_error() {
+    let mut lisp = init(1024);
+    let src = "(make-vector 9223372036854775807 0)";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Space)));
+    assert_eq!(run(&mut lisp, "(vector-length (make-vector 3 0))").unwrap(), "3");
+  }
+
+  #[test]
+  fn make_bytevector_too_large_is_a_space_error() {
+    let mut lisp = init(1024);
+    let src = "(make-bytevector 9223372036854775807 0)";
+    assert!(matches!(run(&mut lisp, src), Err(Error::Space)));
+    assert_eq!(run(&mut lisp, "(make-bytevector 2 7)").unwrap(), "#u8(7 7)");
+  }
+
+  #[test]
+  fn comments_read_as_space() {
+    assert_eq!(eval("(+ 1 ; one (\n 2;two\n)"), "3");
+    assert_eq!(eval("(list #\\; 1)"), "(#\\; 1)");
+  }
+
+  #[test]
+  fn steps_count_each_eval_once() {
+    let mut lisp = init(1024);
+    let before = lisp.step_count();
+    run(&mut lisp, "(+ 1 2)").unwrap();
+    assert_eq!(lisp.step_count() - before, 3);
+    // Six steps out here, and four in the sandbox.
+    let form = lisp.read("(list (sandbox-eval \"(+ 1 2)\" 1000) 4 5)").unwrap()[0];
+    let env = lisp.global_env();
+    assert!(matches!(lisp.call_with_budget(form, env, 9), Err(Error::Time)));
+    assert!(lisp.call_with_budget(form, env, 10).is_ok());
+  }
+
+  #[test]
+  fn collect_garbage_reports_free_runs() {
+    let mut lisp = init(1024);
+    let runs = |report: String| report.trim_matches(['(', ')']).split(' ').nth(3).unwrap().parse::<usize>().unwrap();
+    let before = runs(run(&mut lisp, "(collect-garbage)").unwrap());
+    let mut kept = vec![];
+    for i in 0..200 {
+      let x = lisp.heap.put(Object::Str(Rc::from("x"))).unwrap();
+      if i % 2 == 0 {
+        kept.push(lisp.root(x));
+      }
+    }
+    let after = runs(run(&mut lisp, "(collect-garbage)").unwrap());
+    assert!(after >= before + 90, "{} {}", before, after);
+  }
+
+  #[test]
+  fn reserve_makes_room_for_a_burst() {
+    let mut lisp = init(1024);
+    let capacity = lisp.stats().capacity + 4096;
+    lisp.reserve(capacity).unwrap();
+    assert_eq!(lisp.stats().capacity, capacity);
+    run(&mut lisp, "($define! xs (vector->list (make-vector 2000 0)))").unwrap();
+    assert_eq!(lisp.stats().capacity, capacity);
+  }
+
+  #[test]
+  fn a_frozen_pair_cant_be_changed_in_place() {
+    let mut lisp = init(1024);
+    run(&mut lisp, "($define! x (freeze! (list 1 2)))").unwrap();
+    let x = lisp.load_string("x").unwrap()[0];
+    let object = lisp.heap.get(x).unwrap();
+    assert!(matches!(lisp.heap.set(x, object), Err(Error::Guard)));
+    assert_eq!(run(&mut lisp, "x").unwrap(), "(1 2)");
+  }
+
+  #[test]
+  fn copy_on_write_leaves_other_references_intact() {
+    let mut lisp = init(1024);
+    run(&mut lisp, "($define! x (freeze! (list 1 2))) ($define! y x) ($define! z (set-fst! x 9))").unwrap();
+    assert_eq!(run(&mut lisp, "z").unwrap(), "(9 2)");
+    assert_eq!(run(&mut lisp, "x").unwrap(), "(1 2)");
+    assert_eq!(run(&mut lisp, "y").unwrap(), "(1 2)");
+    assert_eq!(run(&mut lisp, "(eq? (snd z) (snd x))").unwrap(), "#t");
+    assert_eq!(run(&mut lisp, "(eq? (set-fst! z 8) z)").unwrap(), "#t");
+  }
+
+  #[test]
+  fn sorted_bindings_are_alphabetical() {
+    let body = "($define! c 1) ($define! a 2) ($define! b 3) (environment-bindings (($vau () e e)) #t)";
+    assert_eq!(eval(&format!("(($lambda () {}))", body)), "((a * 2) (b * 3) (c * 1))");
+    let mut lisp = init(1024);
+    let env = lisp.global_env();
+    let mut names = vec![];
+    for (name, _) in lisp.bindings(env, true).unwrap() {
+      let mut buf = String::new();
+      lisp.show(name, &mut buf).unwrap();
+      names.push(buf);
+    }
+    assert!(names.len() > 100);
+    assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+  }
+
+  #[test]
+  fn calls_without_the_call_cache_follow_new_bindings() {
+    let mut lisp = init(1024);
+    lisp.set_call_cache(false);
+    run(&mut lisp, "($define! f ($lambda () 1)) ($define! g ($lambda () (f)))").unwrap();
+    assert_eq!(run(&mut lisp, "(g)").unwrap(), "1");
+    run(&mut lisp, "($define! f ($lambda () 2))").unwrap();
+    assert_eq!(run(&mut lisp, "(g)").unwrap(), "2");
+  }
+
+  #[test]
+  fn do_and_parameterize_share_one_list_procedure() {
+    let mut lisp = init(1024);
+    let lists = |lisp: &V0| lisp.heap.nodes.iter().filter(|node| matches!(node,
+      Node::Some(Object::Proc(Proc::Nat(Nat::ListProc)), _) | Node::Mark(Object::Proc(Proc::Nat(Nat::ListProc)), _)
+    )).count();
+    let before = lists(&lisp);
+    assert_eq!(run(&mut lisp, "(do ((i 0 (+ i 1))) ((= i 100) i))").unwrap(), "100");
+    assert_eq!(lists(&lisp), before);
+    run(&mut lisp, "($define! p (make-parameter 1))").unwrap();
+    assert_eq!(run(&mut lisp, "(parameterize ((p 2)) (p))").unwrap(), "2");
+    assert_eq!(lists(&lisp), before);
+  }
+
+  #[test]
+  fn unimplemented_built_ins_are_unbound() {
+    assert_eq!(eval("(bound? ($quote shift))"), "#f");
+    assert_eq!(eval("(bound? ($quote reset))"), "#f");
+  }
+
+  #[test]
+  fn debug_eval_steps_to_the_value() {
+    let mut lisp = init(1024);
+    let form = lisp.read("(+ 1 (* 2 3))").unwrap()[0];
+    let env = lisp.global_env();
+    let mut step = lisp.debug_eval(form, env).unwrap();
+    let mut descriptions = vec![];
+    while let DebugStep::Step { ref description, .. } = step {
+      descriptions.push(description.clone());
+      step = lisp.debug_continue(&step).unwrap();
+    }
+    let value = match step {
+      DebugStep::Done(value) => value,
+      _ => unreachable!(),
+    };
+    let mut buf = String::new();
+    lisp.show(value, &mut buf).unwrap();
+    assert_eq!(buf, "7");
+    assert!(descriptions.iter().any(|description| description == "eval (* 2 3)"), "{:?}", descriptions);
+    assert_eq!(descriptions.last().unwrap(), "return 7");
+  }
+
+  #[test]
+  fn debug_continue_refuses_a_stale_step() {
+    let mut lisp = init(1024);
+    let form = lisp.read("(+ 1 (* 2 3))").unwrap()[0];
+    let env = lisp.global_env();
+    let first = lisp.debug_eval(form, env).unwrap();
+    let second = lisp.debug_continue(&first).unwrap();
+    assert!(matches!(lisp.debug_continue(&first), Err(Error::Guard)));
+    assert!(lisp.debug_continue(&second).is_ok());
+    let old = lisp.debug_eval(form, env).unwrap();
+    lisp.debug_eval(form, env).unwrap();
+    assert!(matches!(lisp.debug_continue(&old), Err(Error::Guard)));
+    let form = lisp.read("(car 1)").unwrap()[0];
+    let mut step = lisp.debug_eval(form, env);
+    while let Ok(ref paused @ DebugStep::Step { .. }) = step {
+      step = lisp.debug_continue(paused);
+    }
+    assert!(step.is_err());
+  }
+
+  #[test]
+  fn compact_moves_live_nodes_and_rewrites_pointers() {
+    let mut lisp = init(1024);
+    run(&mut lisp, "($define! garbage (vector->list (make-vector 500 0))) ($define! garbage 0)").unwrap();
+    run(&mut lisp, "($define! xs (list 1 (list->vector (list 2 3)) \"four\")) ($define! f ($lambda (n) (pair n xs)))").unwrap();
+    let kept = lisp.heap.put(Object::Str(Rc::from("kept"))).unwrap();
+    let kept = lisp.root(kept);
+    lisp.compact();
+    assert_eq!(lisp.stats().free_runs, 1);
+    assert_eq!(run(&mut lisp, "(f 0)").unwrap(), "(0 1 #(2 3) \"four\")");
+    let mut buf = String::new();
+    lisp.show(kept.value(), &mut buf).unwrap();
+    assert_eq!(buf, "\"kept\"");
+    assert_eq!(run(&mut lisp, "(validate-heap)").unwrap(), "#t");
+  }
+
+  #[test]
+  fn syntax_rules_macros_are_hygienic() {
+    let mut lisp = init(1024);
+    run(&mut lisp, "(define-syntax my-or (syntax-rules () ((_ a b) (($lambda (t) ($if t t b)) a))))").unwrap();
+    run(&mut lisp, "(define-syntax my-list (syntax-rules () ((_ x ...) (list x ...))))").unwrap();
+    assert_eq!(run(&mut lisp, "($define! t 5) (my-or #f t)").unwrap(), "5");
+    assert_eq!(run(&mut lisp, "(my-list 1 2 3)").unwrap(), "(1 2 3)");
+    assert_eq!(run(&mut lisp, "(($lambda (list) (my-list 1 2)) 0)").unwrap(), "(1 2)");
+  }
+
+  #[test]
+  fn modules_share_only_their_exports() {
+    let mut lisp = init(1024);
+    let src = "(define-module (util math)
+      ($define! square ($lambda (x) (* x x)))
+      ($define! hidden 1)
+      (export square))";
+    run(&mut lisp, src).unwrap();
+    assert_eq!(run(&mut lisp, "(bound? ($quote square))").unwrap(), "#f");
+    assert_eq!(run(&mut lisp, "(import (util math)) (square 5)").unwrap(), "25");
+    assert_eq!(run(&mut lisp, "(bound? ($quote hidden))").unwrap(), "#f");
+    assert!(matches!(run(&mut lisp, "(import (no such))"), Err(Error::Unbound(_))));
+  }
+
+  #[test]
+  fn dynamic_wind_runs_its_thunks_around_call_cc_jumps() {
+    let mut lisp = init(1024);
+    run(&mut lisp, "($define! log (list ())) ($define! note ($lambda (x) (set-fst! log (pair x (fst log)))))").unwrap();
+    let src = "(call/cc ($lambda (k)
+      (dynamic-wind
+        ($lambda () (note 1))
+        ($lambda () ($sequence (note 2) (k 0) (note 9)))
+        ($lambda () (note 3)))))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "0");
+    assert_eq!(run(&mut lisp, "(fst log)").unwrap(), "(3 2 1)");
+    run(&mut lisp, "(set-fst! log ())").unwrap();
+    let src = "(($lambda ()
+      ($sequence
+        ($define! k (dynamic-wind
+          ($lambda () (note ($quote in)))
+          ($lambda () (call/cc ($lambda (c) c)))
+          ($lambda () (note ($quote out)))))
+        ($if (number? k) (fst log) (k 0)))))";
+    assert_eq!(run(&mut lisp, src).unwrap(), "(out in out in)");
+  }
+
+  #[test]
+  fn cycles_show_as_cycle() {
+    let mut lisp = init(1024);
+    assert_eq!(run(&mut lisp, "($define! x (list 1 2)) (set-fst! x x) x").unwrap(), "(#<cycle> 2)");
+    assert_eq!(run(&mut lisp, "($define! y (list 1)) (list y y)").unwrap(), "((1) (1))");
+  }
+
+  #[test]
+  fn pretty_show_breaks_long_lists() {
+    let mut lisp = init(1024);
+    let value = lisp.load_string("(list 11111 (list 22222 33333) 44444)").unwrap()[0];
+    let mut buf = String::new();
+    lisp.pretty_show(value, 80, &mut buf).unwrap();
+    assert_eq!(buf, "(11111 (22222 33333) 44444)");
+    let mut buf = String::new();
+    lisp.pretty_show(value, 16, &mut buf).unwrap();
+    assert_eq!(buf, "(11111\n (22222 33333)\n 44444)");
+    let mut buf = String::new();
+    lisp.pretty_show(value, 8, &mut buf).unwrap();
+    assert_eq!(buf, "(11111\n (22222\n  33333)\n 44444)");
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn serde_matches_to_json_and_read_json() {
+    use serde::de::DeserializeSeed;
+    let mut lisp = init(1024);
+    let value = lisp.load_string("(list 1 \"two\" ($quote three) (pair 4 5) (list->vector (list #t #f)))").unwrap()[0];
+    let value = lisp.root(value);
+    let mut json = String::new();
+    lisp.to_json(value.value(), &mut json).unwrap();
+    assert_eq!(serde_json::to_string(&lisp.serializable(value.value())).unwrap(), json);
+    let back = lisp.seed().deserialize(&mut serde_json::Deserializer::from_str(&json)).unwrap();
+    let (mut expected, mut got) = (String::new(), String::new());
+    lisp.show(value.value(), &mut expected).unwrap();
+    lisp.show(back, &mut got).unwrap();
+    assert_eq!(got, expected);
+    let cycle = lisp.load_string("($define! x (list 1)) (set-fst! x x) x").unwrap()[2];
+    assert!(serde_json::to_string(&lisp.serializable(cycle)).is_err());
+  }
+
+  #[cfg(feature = "bignum")]
+  #[test]
+  fn bignums_read_compute_and_show_exactly() {
+    assert_eq!(eval("(* 99999999999 99999999999)"), "9999999999800000000001");
+    assert_eq!(eval("123456789012345678901234567890"), "123456789012345678901234567890");
+    assert_eq!(eval("(/ 100000000000000000000 3)"), "100000000000000000000/3");
+    assert_eq!(eval("(- (* 99999999999 99999999999) 9999999999800000000000)"), "1");
   }
 }
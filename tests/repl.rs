@@ -0,0 +1,85 @@
+// This file is a part of Softmacs.
+// Copyright (C) 2018 Matthew Blount
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public
+// License along with this program.  If not, see
+// <https://www.gnu.org/licenses/.
+
+//! The REPL driven through its standard input.
+
+#![allow(clippy::needless_return)]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// What the REPL writes to standard output and error for `input`.
+fn repl(input: &str) -> (String, String) {
+  let mut child = Command::new(env!("CARGO_BIN_EXE_softmacs"))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+  let output = child.wait_with_output().unwrap();
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  return (stdout, stderr);
+}
+
+#[test]
+fn paste_shows_only_the_last_value() {
+  let (stdout, _) = repl(":paste\n($define! a 40)\n(+ a 1)\n(+ a 2)\n:end\n(+ a 3)\n");
+  assert!(stdout.contains("$0 = 42"), "{}", stdout);
+  assert!(!stdout.contains("41"), "{}", stdout);
+  assert!(stdout.contains("$1 = 43"), "{}", stdout);
+}
+
+#[test]
+fn paste_stops_at_the_first_error() {
+  let (stdout, stderr) = repl(":paste\n(+ 1 2)\n(no-such-name)\n(+ 3 4)\n:end\n");
+  assert!(stderr.contains("error"), "{}", stderr);
+  assert!(!stdout.contains('3') && !stdout.contains('7'), "{}", stdout);
+}
+
+#[test]
+fn char_literal_brackets_do_not_continue_the_input() {
+  let (stdout, _) = repl("(list #\\( #\\] 1)\n(+ 1 2)\n");
+  assert!(stdout.contains("$0 = (#\\( #\\] 1)"), "{}", stdout);
+  assert!(stdout.contains("$1 = 3"), "{}", stdout);
+  assert!(!stdout.contains(".."), "{}", stdout);
+}
+
+#[test]
+fn comments_are_skipped() {
+  let (stdout, stderr) = repl("(+ 1 ; (\n 2) ; done\n(+ 3 4)\n");
+  assert!(stdout.contains("$0 = 3"), "{}", stdout);
+  assert!(stdout.contains("$1 = 7"), "{}", stdout);
+  assert!(stderr.is_empty(), "{}", stderr);
+}
+
+#[test]
+fn step_and_continue_walk_an_evaluation() {
+  let mut input = String::from(":continue\n:step (+ 1 (* 2 3))\n");
+  for _ in 0..12 {
+    input.push_str(":continue\n");
+  }
+  input.push_str(":continue\n");
+  let (stdout, stderr) = repl(&input);
+  assert!(stderr.starts_with("error: nothing to continue"), "{}", stderr);
+  assert!(stdout.contains("; apply <procedure> to (1 (* 2 3))"), "{}", stdout);
+  assert!(stdout.contains("; eval (* 2 3)"), "{}", stdout);
+  assert!(stdout.contains("; return 7"), "{}", stdout);
+  assert!(stdout.contains("$0 = 7"), "{}", stdout);
+  assert_eq!(stderr.matches("nothing to continue").count(), 1, "{}", stderr);
+}